@@ -0,0 +1,301 @@
+#![cfg(unix)]
+
+use std::ffi::{c_char, c_uint, c_void, CStr, CString, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::ptr;
+use std::slice;
+
+use custom_mutator::CustomMutator;
+use log::error;
+
+use crate::auto_driver_mutator::AutoDriverMutator;
+
+/// State behind the `data` pointer AFL++ passes to every hook.
+///
+/// The hooks are exported here instead of through `custom_mutator::export_mutator!`,
+/// which does not forward the trim hooks, so that trimming works on the same instance.
+struct AflContext {
+    mutator: AutoDriverMutator,
+    /// Keeps the string returned by `afl_custom_describe` alive until the next call
+    description: CString,
+}
+
+impl AflContext {
+    /// # Safety
+    ///
+    /// `data` is null or a pointer returned by [`afl_custom_init`] that was not passed to
+    /// [`afl_custom_deinit`] yet, and no other reference to its context is alive
+    unsafe fn from_data<'a>(data: *mut c_void) -> Option<&'a mut AflContext> {
+        (data as *mut AflContext).as_mut()
+    }
+}
+
+/// Borrows `size` bytes at `data`, which may be null if `size` is 0
+///
+/// # Safety
+///
+/// `data` is valid for reads of `size` bytes unless it is null
+unsafe fn buffer<'a>(data: *const u8, size: usize) -> &'a [u8] {
+    if data.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(data, size)
+    }
+}
+
+/// # Safety
+///
+/// `data` is null or valid for reads and writes of `size` bytes
+unsafe fn buffer_mut<'a>(data: *mut u8, size: usize) -> &'a mut [u8] {
+    if data.is_null() {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(data, size)
+    }
+}
+
+/// Runs the body of the hook `name`, aborting with the panic message instead of unwinding into AFL++,
+/// like the FFI layer of `custom_mutator` does
+fn catch_panic<R>(name: &str, hook: impl FnOnce() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(hook)) {
+        Ok(result) => result,
+        Err(cause) => {
+            let cause = cause
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| cause.downcast_ref::<&str>().copied())
+                .unwrap_or("<cause unknown>");
+            error!("A panic occurred in {}: {}", name, cause);
+            process::abort()
+        }
+    }
+}
+
+/// Sets up the mutator from the environment, returns null if the function API can not be loaded
+#[no_mangle]
+pub extern "C" fn afl_custom_init(_afl: *mut c_void, seed: c_uint) -> *mut c_void {
+    catch_panic("afl_custom_init", || match AutoDriverMutator::init(seed) {
+        Ok(mutator) => Box::into_raw(Box::new(AflContext {
+            mutator,
+            description: CString::default(),
+        })) as *mut c_void,
+        Err(e) => {
+            AutoDriverMutator::handle_error(e);
+            ptr::null_mut()
+        }
+    })
+}
+
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`] and is not used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_deinit(data: *mut c_void) {
+    catch_panic("afl_custom_deinit", || {
+        if !data.is_null() {
+            drop(Box::from_raw(data as *mut AflContext));
+        }
+    })
+}
+
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`], `buf` is valid for reads of `buf_size` bytes
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_fuzz_count(
+    data: *mut c_void,
+    buf: *const u8,
+    buf_size: usize,
+) -> u32 {
+    catch_panic("afl_custom_fuzz_count", || {
+        let Some(context) = AflContext::from_data(data) else {
+            return 0;
+        };
+        match context.mutator.fuzz_count(buffer(buf, buf_size)) {
+            Ok(count) => count,
+            Err(e) => {
+                AutoDriverMutator::handle_error(e);
+                0
+            }
+        }
+    })
+}
+
+/// Mutates the input in `buf` and points `out_buf` at the result, which stays valid until the next call.
+/// Returns 0 if the mutation is skipped.
+///
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`], `buf` is valid for reads and writes of `buf_size` bytes,
+/// `add_buf` is null or valid for reads of `add_buf_size` bytes and `out_buf` is valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_fuzz(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *const u8,
+    add_buf: *const u8,
+    add_buf_size: usize,
+    max_size: usize,
+) -> usize {
+    catch_panic("afl_custom_fuzz", || {
+        let Some(context) = AflContext::from_data(data) else {
+            return 0;
+        };
+        let add_buff = (!add_buf.is_null()).then(|| buffer(add_buf, add_buf_size));
+        match context
+            .mutator
+            .fuzz(buffer_mut(buf, buf_size), add_buff, max_size)
+        {
+            Ok(Some(mutated)) => {
+                *out_buf = mutated.as_ptr();
+                mutated.len()
+            }
+            Ok(None) => 0,
+            Err(e) => {
+                AutoDriverMutator::handle_error(e);
+                0
+            }
+        }
+    })
+}
+
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`] and `filename` is a null-terminated path
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_queue_get(data: *mut c_void, filename: *const c_char) -> u8 {
+    catch_panic("afl_custom_queue_get", || {
+        let Some(context) = AflContext::from_data(data) else {
+            return 1;
+        };
+        let filename = OsStr::from_bytes(CStr::from_ptr(filename).to_bytes());
+        match context.mutator.queue_get(filename) {
+            Ok(fuzz) => fuzz as u8,
+            Err(e) => {
+                AutoDriverMutator::handle_error(e);
+                1
+            }
+        }
+    })
+}
+
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`], `filename_new_queue` is a null-terminated path
+/// and `filename_orig_queue` is null or a null-terminated path
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_queue_new_entry(
+    data: *mut c_void,
+    filename_new_queue: *const c_char,
+    filename_orig_queue: *const c_char,
+) -> u8 {
+    catch_panic("afl_custom_queue_new_entry", || {
+        let Some(context) = AflContext::from_data(data) else {
+            return 0;
+        };
+        let filename_new_queue = OsStr::from_bytes(CStr::from_ptr(filename_new_queue).to_bytes());
+        let filename_orig_queue = (!filename_orig_queue.is_null())
+            .then(|| OsStr::from_bytes(CStr::from_ptr(filename_orig_queue).to_bytes()));
+        match context
+            .mutator
+            .queue_new_entry(filename_new_queue, filename_orig_queue)
+        {
+            Ok(changed) => changed as u8,
+            Err(e) => {
+                AutoDriverMutator::handle_error(e);
+                0
+            }
+        }
+    })
+}
+
+/// Returns the description of the last mutation, which stays valid until the next call
+///
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`]
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_describe(
+    data: *mut c_void,
+    max_description_len: usize,
+) -> *const c_char {
+    catch_panic("afl_custom_describe", || {
+        let Some(context) = AflContext::from_data(data) else {
+            return ptr::null();
+        };
+        let description = match context.mutator.describe(max_description_len) {
+            Ok(Some(description)) => CString::new(description).ok(),
+            Ok(None) => None,
+            Err(e) => {
+                AutoDriverMutator::handle_error(e);
+                None
+            }
+        };
+        match description {
+            Some(description) => {
+                context.description = description;
+                context.description.as_ptr()
+            }
+            None => ptr::null(),
+        }
+    })
+}
+
+/// Starts trimming the queue entry and returns the number of trimming steps
+///
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`] and `buf` is valid for reads of `buf_size` bytes
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_init_trim(
+    data: *mut c_void,
+    buf: *const u8,
+    buf_size: usize,
+) -> i32 {
+    catch_panic("afl_custom_init_trim", || {
+        let Some(context) = AflContext::from_data(data) else {
+            return -1;
+        };
+        context
+            .mutator
+            .init_trim(buffer(buf, buf_size))
+            .min(i32::MAX as u32) as i32
+    })
+}
+
+/// Points `out_buf` at the trimmed queue entry of the current step, which stays valid until the next call
+///
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`] and `out_buf` is valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_trim(data: *mut c_void, out_buf: *mut *const u8) -> usize {
+    catch_panic("afl_custom_trim", || {
+        let Some(context) = AflContext::from_data(data) else {
+            // AFL++ reports a null buffer as a failed trim
+            *out_buf = ptr::null();
+            return 0;
+        };
+        let trimmed = context.mutator.trim();
+        *out_buf = trimmed.as_ptr();
+        trimmed.len()
+    })
+}
+
+/// Returns the next trimming step, AFL++ stops with an error on negative values
+///
+/// # Safety
+///
+/// `data` was returned by [`afl_custom_init`]
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_post_trim(data: *mut c_void, success: u8) -> i32 {
+    catch_panic("afl_custom_post_trim", || {
+        let Some(context) = AflContext::from_data(data) else {
+            return -1;
+        };
+        context.mutator.post_trim(success != 0).min(i32::MAX as u32) as i32
+    })
+}
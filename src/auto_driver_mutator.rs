@@ -1,34 +1,52 @@
 #![cfg(unix)]
 
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::env;
-use std::fmt::Debug;
+use std::ffi::OsStr;
+use std::fmt::{Debug, Write as _};
+use std::fs;
 use std::fs::File;
-use std::io::{BufReader, Write};
+use std::io::Write;
 
-use custom_mutator::{export_mutator, CustomMutator};
+use custom_mutator::CustomMutator;
 use env_logger::Env;
-use log::{debug, info, log_enabled, trace, Level};
-use serde_json::{Map, Value};
+use log::{debug, error, info, log_enabled, trace, warn, Level};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use crate::c_types::*;
+use crate::error::ApiError;
+use crate::feedback::FunctionFeedback;
+use crate::function_api::{FunctionApi, TypeDeclaration, TypeReference, FUNCTION_API_VERSION};
+use crate::primitives::primitive_types;
+use crate::trim::TrimState;
 
 pub struct AutoDriverMutator {
     pub(crate) decision_bits_per_iteration: usize,
     pub(crate) types: HashMap<String, Type>,
     pub(crate) functions: Vec<Function>,
     pub(crate) chaining_variables_size: usize,
+    /// Source of all randomness, so that the same seed and input always give the same output
+    pub(crate) rng: RefCell<StdRng>,
+    /// Last mutation, which AFL++ adds to the names of new queue entries
+    pub(crate) last_mutation: Cell<Mutation>,
+    /// Label of the last mutation, only formatted when AFL++ asks for it
+    description: String,
+    /// What was learned about each function from new queue entries, in the order of `functions`
+    pub(crate) feedback: RefCell<Vec<FunctionFeedback>>,
     fuzz_vector: Vec<u8>,
+    pub(crate) trim_state: TrimState,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(crate) struct FunctionCall<'a> {
     pub(crate) function: &'a Function,
     pub(crate) chain_return_type: Option<bool>,
     pub(crate) arguments: Vec<FunctionArgument>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum FunctionArgument {
     Basic(BasicType),
     FuzzInput(Type),
@@ -36,369 +54,424 @@ pub(crate) enum FunctionArgument {
     PermanentlyChained,
 }
 
-impl CustomMutator for AutoDriverMutator {
-    type Error = ();
+/// Kind of a mutation and what it changed, e.g. `mutate_arg` of parameter 1
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Mutation {
+    pub(crate) kind: &'static str,
+    pub(crate) target: MutationTarget,
+}
 
-    fn init(_seed: u32) -> Result<Self, Self::Error> {
-        let _ = env_logger::Builder::from_env(Env::default().default_filter_or("debug")).try_init();
-        info!("Initializing AutoDriver mutator!");
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum MutationTarget {
+    None,
+    /// Index into the functions of the mutator
+    Function(usize),
+    /// Index of the parameter
+    Argument(usize),
+}
 
-        // Open file
-        let function_api_location = env::var("AUTO_DRIVER_FUNCTION_API_PATH").expect("Missing AUTO_DRIVER_FUNCTION_API_PATH environmental variable that specifies the location of the fuzz-driver-function-api-layout-json");
+impl AutoDriverMutator {
+    /// Builds the mutator from the fuzz-driver-function-api-layout-json at `function_api_location`,
+    /// for the data model in `AUTO_DRIVER_DATA_MODEL` if it is set
+    pub fn from_function_api(function_api_location: &str) -> Result<Self, ApiError> {
         info!("Reading API from: {}", function_api_location);
-        let file = File::open(&function_api_location)
-            .unwrap_or_else(|_| panic!("Could not open {}", function_api_location));
-        let reader = BufReader::new(file);
-        let json: Value = serde_json::from_reader(reader).unwrap();
+        Self::new(&FunctionApi::from_file_for_target(function_api_location)?)
+    }
+
+    /// Builds the mutator from an already parsed function API
+    pub fn new(api: &FunctionApi) -> Result<Self, ApiError> {
+        if api.version > FUNCTION_API_VERSION {
+            return Err(ApiError::UnsupportedVersion {
+                version: api.version,
+            });
+        }
 
         // Parse decision bits per iteration
-        let decision_bits_per_iteration =
-            json["decision_bits_per_iteration"].as_u64().unwrap() as usize;
+        let decision_bits_per_iteration = api.decision_bits_per_iteration;
 
         // Parse types
         info!("Parsing types:");
         let mut declarations = HashMap::<String, Option<Type>>::new();
-        for declared_type in json["types"].as_array().unwrap() {
-            info!("Declared type: {}", declared_type);
-            let type_name = declared_type["name"].as_str().unwrap();
-            assert_eq!(declarations.insert(String::from(type_name), None), None);
+        for (index, declared_type) in api.types.iter().enumerate() {
+            info!("Declared type: {:?}", declared_type);
+            let type_name = declared_type.name();
+            if declarations.insert(String::from(type_name), None).is_some() {
+                return Err(ApiError::DuplicateDeclaration {
+                    path: format!("types[{}]", index),
+                    name: type_name.to_string(),
+                });
+            }
         }
 
-        // Insert primitive types
-        declarations.insert(
-            String::from("void"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 0],
-            })),
-        );
-        declarations.insert(
-            String::from("char"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 1],
-            })),
-        );
-        declarations.insert(
-            String::from("signed char"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 1],
-            })),
-        );
-        declarations.insert(
-            String::from("unsigned char"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 1],
-            })),
-        );
-        declarations.insert(
-            String::from("short"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 2],
-            })),
-        );
-        declarations.insert(
-            String::from("signed short"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 2],
-            })),
-        );
-        declarations.insert(
-            String::from("unsigned short"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 2],
-            })),
-        );
-        declarations.insert(
-            String::from("int"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 4],
-            })),
-        );
-        declarations.insert(
-            String::from("signed int"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 4],
-            })),
-        );
-        declarations.insert(
-            String::from("unsigned int"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 4],
-            })),
-        );
-        declarations.insert(
-            String::from("long"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 8],
-            })),
-        );
-        declarations.insert(
-            String::from("signed long"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 8],
-            })),
-        );
-        declarations.insert(
-            String::from("unsigned long"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 8],
-            })),
-        );
-        declarations.insert(
-            String::from("signed long long"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 8],
-            })),
-        );
-        declarations.insert(
-            String::from("unsigned long long"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 8],
-            })),
-        );
-        declarations.insert(
-            String::from("float"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 4],
-            })),
-        );
-        declarations.insert(
-            String::from("double"),
-            Some(Type::BasicType(BasicType {
-                content: vec![0; 8],
-            })),
-        );
+        // Insert primitive types, declared types of the same name take precedence
+        info!("Data model: {:?}", api.data_model);
+        for (type_name, primitive_type) in primitive_types(api.data_model) {
+            if declarations.contains_key(&type_name) {
+                info!("Declared type {} replaces the primitive type", type_name);
+                continue;
+            }
+            declarations.insert(type_name, Some(primitive_type));
+        }
         // Insert function_prototype dummy
-        declarations.insert(
-            String::from("function_pointer"),
-            Some(Type::FunctionPointer),
-        );
+        declarations
+            .entry(String::from("function_pointer"))
+            .or_insert(Some(Type::FunctionPointer));
 
-        // Iterative type resolution
+        // Recursive type resolution
         info!("Resolving types:");
-        while declarations.values().any(|v| v.is_none()) {
-            let lookup_map = declarations.clone();
-            for (type_name, type_option) in declarations.iter_mut() {
-                if type_option.is_none() {
-                    *type_option = resolve_type(type_name, &json, &lookup_map);
+        let mut type_names: Vec<String> = declarations.keys().cloned().collect();
+        type_names.sort();
+        for type_name in type_names {
+            lookup_declaration(
+                &type_name,
+                "types",
+                &api.types,
+                &mut declarations,
+                &mut Vec::new(),
+            )?;
+        }
+
+        /// Returns the resolved declaration of `type_name`, resolving it and all types it
+        /// contains by value first if necessary.
+        /// `resolution_chain` holds the types currently being resolved to detect cycles.
+        fn lookup_declaration(
+            type_name: &str,
+            path: &str,
+            declared_types: &[TypeDeclaration],
+            declarations: &mut HashMap<String, Option<Type>>,
+            resolution_chain: &mut Vec<String>,
+        ) -> Result<Type, ApiError> {
+            match declarations.get(type_name) {
+                None => Err(ApiError::UndeclaredType {
+                    path: path.to_string(),
+                    name: type_name.to_string(),
+                }),
+                Some(Some(t)) => Ok(t.clone()),
+                Some(None) => {
+                    if let Some(start) = resolution_chain.iter().position(|t| t == type_name) {
+                        let mut chain = resolution_chain[start..].to_vec();
+                        chain.push(type_name.to_string());
+                        return Err(ApiError::CyclicDeclaration {
+                            path: path.to_string(),
+                            chain,
+                        });
+                    }
+                    resolution_chain.push(type_name.to_string());
+                    let t =
+                        resolve_type(type_name, declared_types, declarations, resolution_chain)?;
+                    resolution_chain.pop();
+                    declarations.insert(type_name.to_string(), Some(t.clone()));
+                    Ok(t)
                 }
             }
         }
 
         fn resolve_type(
             type_name: &str,
-            json: &Value,
-            declarations: &HashMap<String, Option<Type>>,
-        ) -> Option<Type> {
-            let datatype = json["types"]
-                .as_array()
-                .unwrap()
+            declared_types: &[TypeDeclaration],
+            declarations: &mut HashMap<String, Option<Type>>,
+            resolution_chain: &mut Vec<String>,
+        ) -> Result<Type, ApiError> {
+            let (index, datatype) = declared_types
                 .iter()
-                .find(|current_type| current_type["name"].as_str().unwrap() == type_name)
-                .unwrap();
-
-            info!("Looking up: {}", datatype["name"]);
-            match datatype["type"].as_str().unwrap() {
-                "struct" => {
+                .enumerate()
+                .find(|(_, current_type)| current_type.name() == type_name)
+                .expect("Every declaration stems from a declared type");
+            let path = format!("types[{}]", index);
+
+            info!("Looking up: {}", type_name);
+            match datatype {
+                TypeDeclaration::Struct { fields, .. } => {
                     let mut elements = Vec::<Type>::new();
-                    for t in datatype["fields"].as_array().unwrap().iter() {
-                        info!("Looking up struct field: {}", t);
-                        // Simple declaration
-                        if let Some(type_name) = t.as_str() {
-                            match declarations.get(type_name).unwrap() {
-                                Some(t) => elements.push(t.clone()),
-                                None => return None,
-                            }
-                        }
-                        // Array declaration
-                        else {
-                            match resolve_array_or_pointer_type(
-                                t.as_object().unwrap(),
-                                declarations,
-                            ) {
-                                Some(t) => elements.push(t),
-                                None => return None,
-                            }
-                        }
+                    for (i, t) in fields.iter().enumerate() {
+                        info!("Looking up struct field: {:?}", t);
+                        elements.push(resolve_type_reference(
+                            t,
+                            &format!("{}.fields[{}]", path, i),
+                            declared_types,
+                            declarations,
+                            resolution_chain,
+                        )?);
                     }
-                    Some(Type::Struct(Struct { types: elements }))
+                    Ok(Type::Struct(Struct { types: elements }))
                 }
-                "enum" => Some(Type::Enum(Enum { enum_variant: 0 })),
-                "union" => {
+                TypeDeclaration::Enum {
+                    size,
+                    constants,
+                    flags,
+                    ..
+                } => {
+                    if ![1, 2, 4, 8].contains(size) {
+                        return Err(ApiError::InvalidField {
+                            path: format!("{}.size", path),
+                            expected: "an enum size of 1, 2, 4 or 8 bytes",
+                        });
+                    }
+                    let constants: Vec<i64> = constants
+                        .iter()
+                        .map(|constant| Enum::truncate(constant.value, *size))
+                        .collect();
+                    Ok(Type::Enum(Enum {
+                        enum_variant: constants.first().copied().unwrap_or(0),
+                        size: *size,
+                        constants,
+                        flags: *flags,
+                    }))
+                }
+                TypeDeclaration::Union { fields, .. } => {
+                    // The union variant of the input has to select one of the fields
+                    if fields.is_empty() {
+                        return Err(ApiError::InvalidField {
+                            path: format!("{}.fields", path),
+                            expected: "at least one union field",
+                        });
+                    }
                     let mut union_fields = Vec::<Type>::new();
-                    for t in datatype["fields"].as_array().unwrap().iter() {
-                        info!("Looking up union field: {}", t);
-                        // Simple declaration
-                        if let Some(type_name) = t.as_str() {
-                            match declarations.get(type_name).expect("Undeclared type") {
-                                Some(t) => union_fields.push(t.clone()),
-                                None => {
-                                    return None;
-                                }
-                            }
-                        }
-                        // Array declaration
-                        else {
-                            match resolve_array_or_pointer_type(
-                                t.as_object().unwrap(),
-                                declarations,
-                            ) {
-                                Some(t) => union_fields.push(t),
-                                None => return None,
-                            }
-                        }
+                    for (i, t) in fields.iter().enumerate() {
+                        info!("Looking up union field: {:?}", t);
+                        union_fields.push(resolve_type_reference(
+                            t,
+                            &format!("{}.fields[{}]", path, i),
+                            declared_types,
+                            declarations,
+                            resolution_chain,
+                        )?);
                     }
-                    Some(Type::Union(Union {
+                    Ok(Type::Union(Union {
                         union_variant: 0,
                         union_fields,
                     }))
                 }
-                "typedef" => {
-                    if let Some(underlying) = datatype["underlying"].as_str() {
-                        declarations
-                            .get(underlying)
-                            .expect("Undeclared type")
-                            .as_ref()
-                            .map(|t| {
-                                Type::Typedef(TypeDef {
-                                    internal_type: Box::new(t.clone()),
-                                })
-                            })
+                TypeDeclaration::Typedef { underlying, .. } => {
+                    let underlying_path = format!("{}.underlying", path);
+                    if let TypeReference::Named(underlying) = underlying {
+                        let t = lookup_declaration(
+                            underlying,
+                            &underlying_path,
+                            declared_types,
+                            declarations,
+                            resolution_chain,
+                        )?;
+                        Ok(Type::Typedef(TypeDef {
+                            internal_type: Box::new(t),
+                        }))
                     } else {
-                        resolve_array_or_pointer_type(
-                            datatype["underlying"].as_object().unwrap(),
+                        resolve_type_reference(
+                            underlying,
+                            &underlying_path,
+                            declared_types,
                             declarations,
+                            resolution_chain,
                         )
                     }
                 }
-                "function_pointer" => Some(Type::FunctionPointer),
-                _ => {
-                    panic!("Unknown type: {}", datatype)
-                }
+                TypeDeclaration::FunctionPointer { .. } => Ok(Type::FunctionPointer),
             }
         }
-        fn resolve_array_or_pointer_type(
-            t: &Map<String, Value>,
-            declarations: &HashMap<String, Option<Type>>,
-        ) -> Option<Type> {
-            if let Some(array_element) = t.get("array_element") {
-                let array_length = t["length"].as_u64().unwrap() as usize;
-                return if let Some(t) = array_element.as_str() {
-                    declarations.get(t).unwrap().as_ref().map(|t| {
-                        Type::Array(Array {
-                            elements: vec![t.clone(); array_length],
-                        })
-                    })
-                } else {
-                    resolve_array_or_pointer_type(array_element.as_object().unwrap(), declarations)
-                        .map(|t| {
-                            Type::Array(Array {
-                                elements: vec![t; array_length],
-                            })
-                        })
-                };
-            } else if let Some(pointee) = t.get("pointee") {
-                return if let Some(t) = pointee.as_str() {
-                    Some(Type::Pointer(Pointer {
-                        target_type_id: Some(t.to_string()),
-                        elements: vec![],
+        fn resolve_type_reference(
+            t: &TypeReference,
+            path: &str,
+            declared_types: &[TypeDeclaration],
+            declarations: &mut HashMap<String, Option<Type>>,
+            resolution_chain: &mut Vec<String>,
+        ) -> Result<Type, ApiError> {
+            match t {
+                // Simple declaration
+                TypeReference::Named(type_name) => lookup_declaration(
+                    type_name,
+                    path,
+                    declared_types,
+                    declarations,
+                    resolution_chain,
+                ),
+                // Array declaration
+                TypeReference::Array {
+                    array_element,
+                    length,
+                } => {
+                    let element = resolve_type_reference(
+                        array_element,
+                        &format!("{}.array_element", path),
+                        declared_types,
+                        declarations,
+                        resolution_chain,
+                    )?;
+                    Ok(Type::Array(Array {
+                        elements: vec![element; *length],
                     }))
-                } else {
-                    resolve_array_or_pointer_type(pointee.as_object().unwrap(), declarations).map(
-                        |t| {
-                            Type::Pointer(Pointer {
-                                target_type_id: None,
-                                elements: vec![t],
-                            })
-                        },
-                    )
-                };
-            } else {
-                panic!("Unknown type: {:?}", t)
+                }
+                // Pointer declaration
+                TypeReference::Pointer { pointee } => {
+                    let pointee_path = format!("{}.pointee", path);
+                    if let TypeReference::Named(t) = pointee.as_ref() {
+                        // Named pointees are resolved lazily through their target_type_id,
+                        // which allows self-referential types such as linked lists
+                        if !declarations.contains_key(t) {
+                            return Err(ApiError::UndeclaredType {
+                                path: pointee_path,
+                                name: t.to_string(),
+                            });
+                        }
+                        Ok(Type::Pointer(Pointer {
+                            target_type_id: Some(t.to_string()),
+                            inline_target: None,
+                            elements: vec![],
+                        }))
+                    } else {
+                        let element = resolve_type_reference(
+                            pointee,
+                            &pointee_path,
+                            declared_types,
+                            declarations,
+                            resolution_chain,
+                        )?;
+                        Ok(Type::Pointer(Pointer {
+                            target_type_id: None,
+                            inline_target: Some(Box::new(element.clone())),
+                            elements: vec![element],
+                        }))
+                    }
+                }
             }
         }
         let types: HashMap<String, Type> = declarations
             .clone()
             .into_iter()
-            .map(|(k, v)| (k, v.expect("Could not resolve type")))
+            .map(|(k, v)| (k, v.expect("All types are resolved at this stage")))
             .collect();
 
         fn lookup_type(
-            t: &Value,
-            types: &HashMap<String, Type>,
-            declarations: &HashMap<String, Option<Type>>,
-        ) -> Type {
-            let mut t = if let Some(type_identifier) = t.as_str() {
-                types[type_identifier].clone()
-            } else {
-                resolve_array_or_pointer_type(t.as_object().unwrap(), declarations)
-                    .expect("All types must be declared and resolved at this stage")
-            };
+            t: &TypeReference,
+            path: &str,
+            declared_types: &[TypeDeclaration],
+            declarations: &mut HashMap<String, Option<Type>>,
+        ) -> Result<Type, ApiError> {
+            let mut t =
+                resolve_type_reference(t, path, declared_types, declarations, &mut Vec::new())?;
             while let Type::Typedef(underlying) = &t {
                 t = *underlying.internal_type.clone();
             }
-            t
+            Ok(t)
         }
 
         // Parse functions
         info!("Parsing functions:");
+        // Every iteration of a fuzz run calls one of the functions
+        if api.functions.is_empty() {
+            return Err(ApiError::InvalidField {
+                path: String::from("functions"),
+                expected: "at least one function",
+            });
+        }
         let mut functions: Vec<Function> = Vec::new();
-        for declared_function in json["functions"].as_array().unwrap() {
-            info!("Parsing function: {}", declared_function);
-            let name = declared_function["name"].as_str().unwrap();
-
-            let return_type = &declared_function["return_type"].as_object().unwrap()["type"];
-            let return_type = lookup_type(return_type, &types, &declarations);
+        for (index, declared_function) in api.functions.iter().enumerate() {
+            info!("Parsing function: {:?}", declared_function);
+            let path = format!("functions[{}]", index);
+            let name = &declared_function.name;
+
+            let return_type = lookup_type(
+                &declared_function.return_type.type_reference,
+                &format!("{}.return_type.type", path),
+                &api.types,
+                &mut declarations,
+            )?;
             info!("Return type: {:?}", return_type);
 
             let mut function = Function {
                 name: name.to_string(),
                 return_type,
                 parameter_types: Vec::new(),
+                declared_parameter_types: Vec::new(),
             };
 
-            for function_parameter in declared_function["parameter_types"].as_array().unwrap() {
-                info!("Looking up function parameter: {}", function_parameter);
-                let opaque = function_parameter.as_object().unwrap()["opaque"]
-                    .as_bool()
-                    .unwrap();
-                let parameter_type = &function_parameter.as_object().unwrap()["type"];
-                let parameter_type = lookup_type(parameter_type, &types, &declarations);
+            for (i, function_parameter) in declared_function.parameter_types.iter().enumerate() {
+                info!("Looking up function parameter: {:?}", function_parameter);
+                let opaque = function_parameter.opaque;
+                let parameter_type = lookup_type(
+                    &function_parameter.type_reference,
+                    &format!("{}.parameter_types[{}].type", path, i),
+                    &api.types,
+                    &mut declarations,
+                )?;
 
                 info!("{}: {:?}", name, parameter_type);
                 info!("Opaque: {}", opaque);
                 if !opaque {
-                    function.parameter_types.push(parameter_type);
+                    function.parameter_types.push(parameter_type.clone());
                 } else {
                     function.parameter_types.push(Type::OpaquePointer);
                 }
+                function.declared_parameter_types.push(parameter_type);
             }
             functions.push(function);
         }
 
+        // Cross-check decision bits per iteration
+        let computed_decision_bits: usize = functions.iter().map(Function::decision_bits).sum();
+        if computed_decision_bits != decision_bits_per_iteration {
+            return Err(ApiError::DecisionBitsMismatch {
+                declared: decision_bits_per_iteration,
+                computed: computed_decision_bits,
+            });
+        }
+
         // Parse chaining variables
         info!("Parsing chaining variables:");
-        let chaining_variables_size = json["minimal_init_chaining_variables_size"]
-            .as_u64()
-            .unwrap() as usize;
+        let chaining_variables_size = api.minimal_init_chaining_variables_size;
         info!(
             "Size of chaining variables section in bytes: {}",
             chaining_variables_size
         );
 
         info!("Function API parsed successfully!\n");
+        let feedback = functions.iter().map(FunctionFeedback::new).collect();
         Ok(Self {
             decision_bits_per_iteration,
             types,
             functions,
             chaining_variables_size,
+            rng: RefCell::new(StdRng::from_entropy()),
+            last_mutation: Cell::new(Mutation {
+                kind: "",
+                target: MutationTarget::None,
+            }),
+            description: String::new(),
+            feedback: RefCell::new(feedback),
             fuzz_vector: Vec::new(),
+            trim_state: TrimState::default(),
         })
     }
 
+    /// Reseeds the mutator, which is seeded from system entropy by [`AutoDriverMutator::new`]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = RefCell::new(StdRng::seed_from_u64(seed));
+    }
+}
+
+impl CustomMutator for AutoDriverMutator {
+    type Error = ApiError;
+
+    fn handle_error(err: Self::Error) {
+        error!("{}", err);
+    }
+
+    fn init(seed: u32) -> Result<Self, Self::Error> {
+        let _ = env_logger::Builder::from_env(Env::default().default_filter_or("debug")).try_init();
+        info!("Initializing AutoDriver mutator!");
+
+        let function_api_location =
+            env::var("AUTO_DRIVER_FUNCTION_API_PATH").map_err(|_| ApiError::MissingApiPath)?;
+        info!("Reading API from: {}", function_api_location);
+        let mut mutator = Self::new(&FunctionApi::from_file_for_target(&function_api_location)?)?;
+        info!("Seed: {}", seed);
+        mutator.set_seed(seed as u64);
+        Ok(mutator)
+    }
+
     fn fuzz<'b, 's: 'b>(
         &'s mut self,
         buffer: &'b mut [u8],
-        _add_buff: Option<&[u8]>,
+        add_buff: Option<&[u8]>,
         max_size: usize,
     ) -> Result<Option<&'b [u8]>, Self::Error> {
         debug!("New fuzz run!");
@@ -412,7 +485,17 @@ impl CustomMutator for AutoDriverMutator {
 
         // Initialize blank fuzz run
         if buffer.len() < self.chaining_variables_size + 2 {
-            self.fuzz_vector = self.serialize_fuzz_run(&Vec::new());
+            let blank_fuzz_run = self.serialize_fuzz_run(&Vec::new());
+            if blank_fuzz_run.len() > max_size {
+                debug!(
+                    "Blank fuzz run of {} bytes does not fit into {} bytes",
+                    blank_fuzz_run.len(),
+                    max_size
+                );
+                return Ok(None);
+            }
+            self.record_mutation("init");
+            self.fuzz_vector = blank_fuzz_run;
             debug!("--Initialized fuzz vector--\n");
             debug!("Output:\n{:?}", self.fuzz_vector);
             debug!("Output length: {}", self.fuzz_vector.len());
@@ -420,7 +503,13 @@ impl CustomMutator for AutoDriverMutator {
         }
 
         // Deserialize input fuzz run
-        let mut called_functions = self.deserialize_fuzz_run(buffer);
+        let mut called_functions = match self.try_deserialize_fuzz_run(buffer) {
+            Ok(called_functions) => called_functions,
+            Err(e) => {
+                debug!("Input does not fit the function API ({}), repairing it", e);
+                self.repair_fuzz_run(buffer)
+            }
+        };
         trace!("Functions before mutation:");
         if log_enabled!(Level::Trace) {
             for function in &called_functions {
@@ -438,8 +527,17 @@ impl CustomMutator for AutoDriverMutator {
             );
         }
 
-        // Mutate the fuzz run
-        self.mutate(&mut called_functions);
+        // Splice with the additional queue entry or mutate the fuzz run,
+        // the queue entry is only decoded if it is spliced
+        let donor = add_buff
+            .filter(|_| self.rng.borrow_mut().gen_ratio(1, 4))
+            .map(|add_buff| self.repair_fuzz_run(add_buff));
+        match donor {
+            Some(donor) if !donor.is_empty() => self.splice(&mut called_functions, donor),
+            _ => self.mutate(&mut called_functions),
+        }
+        self.satisfy_chain(&mut called_functions);
+        let fits = self.shrink_to_fit(&mut called_functions, max_size);
         debug!("Functions after mutation:");
         if log_enabled!(Level::Debug) {
             for function in &called_functions {
@@ -461,13 +559,55 @@ impl CustomMutator for AutoDriverMutator {
             file.write_all(&self.fuzz_vector).unwrap();
         }
 
-        if self.fuzz_vector.len() <= max_size {
+        if fits {
             Ok(Some(&self.fuzz_vector))
         } else {
-            debug!("Skipping mutation as it exceeds max_size!");
+            debug!("Skipping mutation as even an empty fuzz run exceeds max_size!");
             Ok(None)
         }
     }
-}
 
-export_mutator!(AutoDriverMutator);
+    fn queue_new_entry(
+        &mut self,
+        filename_new_queue: &OsStr,
+        _filename_orig_queue: Option<&OsStr>,
+    ) -> Result<bool, Self::Error> {
+        match fs::read(filename_new_queue) {
+            Ok(buffer) => {
+                if !self.learn_from_queue_entry(&buffer) {
+                    debug!(
+                        "New queue entry {:?} does not fit the function API",
+                        filename_new_queue
+                    );
+                }
+            }
+            Err(e) => warn!(
+                "Could not read new queue entry {:?}: {}",
+                filename_new_queue, e
+            ),
+        }
+        // The queue entry itself is left unchanged
+        Ok(false)
+    }
+
+    fn describe(&mut self, max_description: usize) -> Result<Option<&str>, Self::Error> {
+        let mutation = self.last_mutation.get();
+        let description = &mut self.description;
+        description.clear();
+        description.push_str(mutation.kind);
+        match mutation.target {
+            MutationTarget::None => {}
+            MutationTarget::Function(index) => {
+                let _ = write!(description, ":{}", self.functions[index].name);
+            }
+            MutationTarget::Argument(parameter) => {
+                let _ = write!(description, ":{}", parameter);
+            }
+        }
+        let mut length = description.len().min(max_description);
+        while !description.is_char_boundary(length) {
+            length -= 1;
+        }
+        Ok(Some(&description[..length]))
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use env_logger::Env;
+
+use auto_driver_mutator::auto_driver_mutator::AutoDriverMutator;
+use auto_driver_mutator::function_api::FunctionApi;
+use auto_driver_mutator::fuzz_run::{Argument, FuzzRun};
+
+const USAGE: &str = "Usage:
+  auto_driver validate-api <function-api.json>
+  auto_driver decode <function-api.json> <input> [--json]
+  auto_driver encode <function-api.json> <fuzz-run.json> <output>
+  auto_driver check <function-api.json> <queue-directory>
+  auto_driver stats <function-api.json> <corpus-directory>
+
+The data model of the API can be overridden with AUTO_DRIVER_DATA_MODEL, as for the mutator.";
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["validate-api", api] => validate_api(api),
+        ["decode", api, input] => decode(api, input, false),
+        ["decode", api, input, "--json"] => decode(api, input, true),
+        ["encode", api, fuzz_run, output] => encode(api, fuzz_run, output),
+        ["check", api, queue] => check(api, queue),
+        ["stats", api, corpus] => stats(api, corpus),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Checks that the function API is well-formed and fits its decision bits
+fn validate_api(api: &str) -> Result<bool, Box<dyn Error>> {
+    let function_api = FunctionApi::from_file_for_target(api)?;
+    AutoDriverMutator::new(&function_api)?;
+    println!(
+        "{}: {} types, {} functions, {} decision bits per iteration ({:?})",
+        api,
+        function_api.types.len(),
+        function_api.functions.len(),
+        function_api.decision_bits_per_iteration,
+        function_api.data_model
+    );
+    Ok(true)
+}
+
+/// Prints the input as text or JSON
+fn decode(api: &str, input: &str, json: bool) -> Result<bool, Box<dyn Error>> {
+    let mutator = load_mutator(api)?;
+    let fuzz_run = mutator.decode_fuzz_run(&read(input)?)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&fuzz_run)?);
+    } else {
+        print!("{}", fuzz_run);
+    }
+    Ok(true)
+}
+
+/// Writes the fuzz run given as JSON to `output` in the format consumed by the fuzz-driver
+fn encode(api: &str, fuzz_run: &str, output: &str) -> Result<bool, Box<dyn Error>> {
+    let mutator = load_mutator(api)?;
+    let fuzz_run: FuzzRun = serde_json::from_slice(&read(fuzz_run)?)?;
+    let buffer = mutator.encode_fuzz_run(&fuzz_run)?;
+    fs::write(output, buffer).map_err(|e| format!("Could not write {}: {}", output, e))?;
+    Ok(true)
+}
+
+/// Reports every input in `queue` that does not survive a deserialization round-trip
+fn check(api: &str, queue: &str) -> Result<bool, Box<dyn Error>> {
+    let mutator = load_mutator(api)?;
+    let inputs = list_inputs(queue)?;
+    let mut failures = 0;
+    for input in inputs.iter() {
+        let buffer = match read(input) {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                failures += 1;
+                println!("{}", e);
+                continue;
+            }
+        };
+        match mutator.reserialize_fuzz_run(&buffer) {
+            Ok(reserialized) if reserialized == buffer => {}
+            Ok(reserialized) => {
+                failures += 1;
+                println!(
+                    "{}: round-trip changes the input ({} bytes become {} bytes)",
+                    input.display(),
+                    buffer.len(),
+                    reserialized.len()
+                );
+            }
+            Err(e) => {
+                failures += 1;
+                println!("{}: {}", input.display(), e);
+            }
+        }
+    }
+    println!(
+        "{} of {} inputs round-trip",
+        inputs.len() - failures,
+        inputs.len()
+    );
+    Ok(failures == 0)
+}
+
+/// Summarizes call counts and chain usage across all inputs in `corpus`
+fn stats(api: &str, corpus: &str) -> Result<bool, Box<dyn Error>> {
+    #[derive(Default)]
+    struct FunctionStats {
+        calls: usize,
+        chained_returns: usize,
+        chained_arguments: usize,
+        fuzzed_arguments: usize,
+    }
+
+    let mutator = load_mutator(api)?;
+    let inputs = list_inputs(corpus)?;
+    let mut invalid_inputs = 0;
+    let mut total_calls = 0;
+    let mut function_stats = BTreeMap::<String, FunctionStats>::new();
+    for input in inputs.iter() {
+        let buffer = match read(input) {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                invalid_inputs += 1;
+                println!("{}", e);
+                continue;
+            }
+        };
+        let fuzz_run = match mutator.decode_fuzz_run(&buffer) {
+            Ok(fuzz_run) => fuzz_run,
+            Err(_) => {
+                invalid_inputs += 1;
+                continue;
+            }
+        };
+        total_calls += fuzz_run.calls.len();
+        for call in fuzz_run.calls {
+            let entry = function_stats.entry(call.function).or_default();
+            entry.calls += 1;
+            entry.chained_returns += (call.chain_return == Some(true)) as usize;
+            for argument in call.arguments {
+                match argument {
+                    Argument::Chained | Argument::PermanentlyChained => {
+                        entry.chained_arguments += 1
+                    }
+                    Argument::Basic(_) | Argument::FuzzInput(_) => entry.fuzzed_arguments += 1,
+                }
+            }
+        }
+    }
+
+    let valid_inputs = inputs.len() - invalid_inputs;
+    println!(
+        "{} inputs ({} invalid), {} calls, {:.2} calls per valid input",
+        inputs.len(),
+        invalid_inputs,
+        total_calls,
+        total_calls as f64 / valid_inputs.max(1) as f64
+    );
+    println!(
+        "{:<40} {:>10} {:>16} {:>18} {:>18}",
+        "function", "calls", "chained returns", "chained arguments", "fuzzed arguments"
+    );
+    for (function, s) in function_stats.iter() {
+        println!(
+            "{:<40} {:>10} {:>16} {:>18} {:>18}",
+            function, s.calls, s.chained_returns, s.chained_arguments, s.fuzzed_arguments
+        );
+    }
+    Ok(true)
+}
+
+/// Builds the mutator for the same data model as the AFL++ custom mutator
+fn load_mutator(api: &str) -> Result<AutoDriverMutator, Box<dyn Error>> {
+    Ok(AutoDriverMutator::new(&FunctionApi::from_file_for_target(
+        api,
+    )?)?)
+}
+
+fn read(path: impl AsRef<Path>) -> Result<Vec<u8>, String> {
+    fs::read(path.as_ref())
+        .map_err(|e| format!("Could not read {}: {}", path.as_ref().display(), e))
+}
+
+/// Lists the regular files in `directory`, skipping hidden files such as AFL++'s `.state`
+fn list_inputs(directory: &str) -> Result<Vec<PathBuf>, String> {
+    let entries =
+        fs::read_dir(directory).map_err(|e| format!("Could not read {}: {}", directory, e))?;
+    let mut inputs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    inputs.sort();
+    Ok(inputs)
+}
@@ -3,7 +3,7 @@
 use std::cmp::min;
 use std::ops::Range;
 
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
 use rand::seq::{IteratorRandom, SliceRandom};
 use rand::Rng;
 
@@ -47,39 +47,72 @@ pub const INTERESTING_32: [i32; 27] = [
     2147483647,
 ];
 
+/// Interesting single precision float values
+pub const INTERESTING_F32: [f32; 13] = [
+    0.0,
+    -0.0,
+    1.0,
+    -1.0,
+    0.5,
+    f32::EPSILON,
+    f32::MIN_POSITIVE,
+    1e-40, // subnormal
+    f32::MAX,
+    f32::MIN,
+    f32::INFINITY,
+    f32::NEG_INFINITY,
+    f32::NAN,
+];
+/// Interesting double precision float values
+pub const INTERESTING_F64: [f64; 13] = [
+    0.0,
+    -0.0,
+    1.0,
+    -1.0,
+    0.5,
+    f64::EPSILON,
+    f64::MIN_POSITIVE,
+    1e-310, // subnormal
+    f64::MAX,
+    f64::MIN,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+    f64::NAN,
+];
+
 /// Flips a random bit in the input vector
-pub fn bit_flip_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn bit_flip_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let bit = 1 << rng.gen_range(0..8);
     let byte = buf.choose_mut(rng).unwrap();
     *byte ^= bit;
 }
 
 /// Flips a random byte in the input vector
-pub fn byte_flip_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn byte_flip_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let byte = buf.choose_mut(rng).unwrap();
     *byte ^= 0xff;
 }
 
 /// Increments a random byte in the input vector by 1
-pub fn byte_inc_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn byte_inc_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let byte = buf.choose_mut(rng).unwrap();
     *byte = byte.wrapping_add(1);
 }
 
 /// Decrements a random byte in the input vector by 1
-pub fn byte_dec_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn byte_dec_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let byte = buf.choose_mut(rng).unwrap();
     *byte = byte.wrapping_sub(1);
 }
 
 /// Sets a random byte in the input vector to its negative value
-pub fn byte_neg_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn byte_neg_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let byte = buf.choose_mut(rng).unwrap();
     *byte = (!(*byte)).wrapping_add(1);
 }
 
 /// Flips a random bit in a random byte in the input vector
-pub fn byte_rand_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn byte_rand_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let byte = buf.choose_mut(rng).unwrap();
     *byte ^= rng.gen_range(1..=255);
 }
@@ -110,22 +143,22 @@ macro_rules! add_mutator {
 }
 
 /// Adds a random value to a random u8 in the input vector
-pub fn add_mutator_u8(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn add_mutator_u8(buf: &mut [u8], rng: &mut StdRng) {
     add_mutator!(buf, rng, u8);
 }
 
 /// Adds a random value to a random u16 in the input vector
-pub fn add_mutator_u16(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn add_mutator_u16(buf: &mut [u8], rng: &mut StdRng) {
     add_mutator!(buf, rng, u16);
 }
 
 /// Adds a random value to a random u32 in the input vector
-pub fn add_mutator_u32(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn add_mutator_u32(buf: &mut [u8], rng: &mut StdRng) {
     add_mutator!(buf, rng, u32);
 }
 
 /// Adds a random value to a random u64 in the input vector
-pub fn add_mutator_u64(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn add_mutator_u64(buf: &mut [u8], rng: &mut StdRng) {
     add_mutator!(buf, rng, u64);
 }
 
@@ -148,23 +181,23 @@ macro_rules! set_mutator {
 }
 
 /// Inserts an interesting u8 value at a random place in the input vector
-pub fn interesting_set_mutator_u8(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn interesting_set_mutator_u8(buf: &mut [u8], rng: &mut StdRng) {
     set_mutator!(buf, rng, u8, INTERESTING_8);
 }
 
 /// Inserts an interesting u16 value at a random place in the input vector
-pub fn interesting_set_mutator_u16(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn interesting_set_mutator_u16(buf: &mut [u8], rng: &mut StdRng) {
     set_mutator!(buf, rng, u16, INTERESTING_16);
 }
 
 /// Inserts an interesting u32 value at a random place in the input vector
-pub fn interesting_set_mutator_u32(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn interesting_set_mutator_u32(buf: &mut [u8], rng: &mut StdRng) {
     set_mutator!(buf, rng, u32, INTERESTING_32);
 }
 
 /// Generate a range of values where (upon repeated calls) each index is likely to appear in the
 /// provided range as likely as any other value
-fn random_range(buf: &[u8], max_len: usize, rng: &mut ThreadRng) -> Range<usize> {
+fn random_range(buf: &[u8], max_len: usize, rng: &mut StdRng) -> Range<usize> {
     let len = rng.gen_range(1..=min(buf.len(), max_len));
     let mut index2 = rng.gen_range(1..buf.len() + len);
     let index1 = index2.saturating_sub(len);
@@ -175,7 +208,7 @@ fn random_range(buf: &[u8], max_len: usize, rng: &mut ThreadRng) -> Range<usize>
 }
 
 /// Sets range of bytes in the buffer to a value already in the input vector
-pub fn bytes_set_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn bytes_set_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let value = buf.choose(rng).unwrap().to_owned();
     let range = random_range(buf, 16, rng);
     let subslice = &mut buf[range];
@@ -183,7 +216,7 @@ pub fn bytes_set_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
 }
 
 /// Sets range of bytes in the input vector to a random value
-pub fn bytes_random_set_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn bytes_random_set_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let value = rng.gen_range(0..=255);
     let range = random_range(buf, 16, rng);
     let subslice = &mut buf[range];
@@ -191,7 +224,7 @@ pub fn bytes_random_set_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
 }
 
 /// Overwrite a range of bytes in the input vector with a range of bytes from the input vector
-pub fn bytes_copy_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn bytes_copy_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let range = random_range(buf, 16, rng);
     let copy_source = buf[range].to_vec();
     let dest_start_index = rng.gen_range(0..=buf.len().saturating_sub(copy_source.len()));
@@ -200,7 +233,7 @@ pub fn bytes_copy_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
 }
 
 /// Swap a range of bytes in the input vector with a range of bytes from the input vector
-pub fn bytes_swap_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
+pub fn bytes_swap_mutator(buf: &mut [u8], rng: &mut StdRng) {
     let range_a = random_range(buf, 16, rng);
     let mut index_b = rng.gen_range(0..=buf.len().saturating_sub(range_a.len()));
     for index_a in range_a {
@@ -209,4 +242,44 @@ pub fn bytes_swap_mutator(buf: &mut [u8], rng: &mut ThreadRng) {
     }
 }
 
-// Future options: crossover replace / splice
+/// Toggles a `_Bool` between `false` and `true`
+pub fn bool_flip_mutator(buf: &mut [u8], _: &mut StdRng) {
+    buf[0] = (buf[0] == 0) as u8;
+    buf[1..].iter_mut().for_each(|b| *b = 0);
+}
+
+/// Sets a little endian integer of any width to 0, 1 or one of the signed and unsigned extremes
+pub fn boundary_set_mutator(buf: &mut [u8], rng: &mut StdRng) {
+    let last = buf.len() - 1;
+    buf.iter_mut().for_each(|b| *b = 0);
+    match rng.gen_range(0..5) {
+        0 => {}
+        1 => buf[0] = 1,
+        2 => buf.iter_mut().for_each(|b| *b = 0xff), // -1 or unsigned maximum
+        3 => buf[last] = 0x80,                       // signed minimum
+        _ => {
+            // signed maximum
+            buf.iter_mut().for_each(|b| *b = 0xff);
+            buf[last] = 0x7f;
+        }
+    }
+}
+
+/// Negates a little endian two's complement integer of any width
+pub fn negate_mutator(buf: &mut [u8], _: &mut StdRng) {
+    let mut carry = true;
+    for byte in buf.iter_mut() {
+        let (value, overflow) = (!*byte).overflowing_add(carry as u8);
+        *byte = value;
+        carry = overflow;
+    }
+}
+
+/// Sets a float to an interesting value, if the input vector has the size of a `float` or `double`
+pub fn interesting_set_mutator_float(buf: &mut [u8], rng: &mut StdRng) {
+    match buf.len() {
+        4 => buf.copy_from_slice(&INTERESTING_F32.choose(rng).unwrap().to_le_bytes()),
+        8 => buf.copy_from_slice(&INTERESTING_F64.choose(rng).unwrap().to_le_bytes()),
+        _ => {}
+    }
+}
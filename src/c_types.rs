@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use log::debug;
-use rand::prelude::{SliceRandom, ThreadRng};
+use rand::prelude::{SliceRandom, StdRng};
 use rand::Rng;
 
 use crate::{byte_vec_mutator, call_random_function};
@@ -20,7 +20,7 @@ pub(crate) enum Type {
 }
 
 impl Type {
-    pub(crate) fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    pub(crate) fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut StdRng) {
         debug!("Mutating type: {:?}", self);
         match self {
             Type::Array(a) => a.mutate(types, rng),
@@ -34,6 +34,151 @@ impl Type {
             Type::BasicType(b) => b.mutate(types, rng),
         }
     }
+    /// Applies the `index`-th reduction within this value, counting `index` down for every
+    /// reduction passed over. Returns whether a reduction was applied.
+    pub(crate) fn reduce(&mut self, index: &mut usize) -> bool {
+        fn reduce_elements(elements: &mut [Type], index: &mut usize) -> bool {
+            elements.iter_mut().any(|element| element.reduce(index))
+        }
+        fn next(index: &mut usize) -> bool {
+            let hit = *index == 0;
+            *index = index.wrapping_sub(1);
+            hit
+        }
+        match self {
+            Type::Pointer(p) => {
+                if !p.elements.is_empty() && next(index) {
+                    p.elements.truncate(p.elements.len() / 2);
+                    return true;
+                }
+                reduce_elements(&mut p.elements, index)
+            }
+            Type::Array(a) => {
+                let mut zeroed = a.elements.clone();
+                zeroed.iter_mut().for_each(Type::zero);
+                if a.elements != zeroed && next(index) {
+                    a.elements = zeroed;
+                    return true;
+                }
+                reduce_elements(&mut a.elements, index)
+            }
+            Type::Struct(s) => reduce_elements(&mut s.types, index),
+            Type::Union(u) => u.union_fields[u.union_variant].reduce(index),
+            Type::Typedef(t) => t.internal_type.reduce(index),
+            Type::BasicType(b) => {
+                if b.content.iter().any(|&byte| byte != 0) && next(index) {
+                    b.content.fill(0);
+                    return true;
+                }
+                false
+            }
+            Type::OpaquePointer | Type::Enum(_) | Type::FunctionPointer => false,
+        }
+    }
+    /// Empties all pointers and zeroes all basic types within this value
+    fn zero(&mut self) {
+        match self {
+            Type::Pointer(p) => p.elements.clear(),
+            Type::Array(Array { elements: types }) | Type::Struct(Struct { types }) => {
+                types.iter_mut().for_each(Type::zero)
+            }
+            Type::Union(u) => u.union_fields[u.union_variant].zero(),
+            Type::Typedef(t) => t.internal_type.zero(),
+            Type::BasicType(b) => b.content.fill(0),
+            Type::OpaquePointer | Type::Enum(_) | Type::FunctionPointer => {}
+        }
+    }
+    /// Selected variant, if this is a union
+    pub(crate) fn union_variant(&self) -> Option<usize> {
+        match self {
+            Type::Union(u) => Some(u.union_variant),
+            Type::Typedef(t) => t.internal_type.union_variant(),
+            _ => None,
+        }
+    }
+    /// Whether arguments of this type can actually be read from the chain
+    pub(crate) fn is_chainable(&self) -> bool {
+        matches!(
+            self,
+            Type::Array(_) | Type::Pointer(_) | Type::FunctionPointer
+        )
+    }
+    /// Type of the values a pointer of this type points to, with typedefs resolved.
+    /// Arrays are passed as pointers to their first element.
+    pub(crate) fn pointee<'a>(&'a self, types: &'a HashMap<String, Type>) -> Option<&'a Type> {
+        match self {
+            Type::Pointer(p) => Some(p.target_type(types).resolved()),
+            Type::Array(a) => a.elements.first().map(Type::resolved),
+            Type::Typedef(t) => t.internal_type.pointee(types),
+            _ => None,
+        }
+    }
+    /// This type without the typedefs around it
+    pub(crate) fn resolved(&self) -> &Type {
+        match self {
+            Type::Typedef(t) => t.internal_type.resolved(),
+            t => t,
+        }
+    }
+    /// The pointer inside this value holding the most elements, including pointers within pointer elements
+    pub(crate) fn largest_pointer(&mut self) -> Option<&mut Pointer> {
+        match self {
+            Type::Pointer(p) => {
+                let nested = p
+                    .elements
+                    .iter()
+                    .filter_map(Type::largest_pointer_len)
+                    .max();
+                if nested.is_some_and(|nested| nested > p.elements.len()) {
+                    p.elements
+                        .iter_mut()
+                        .filter_map(Type::largest_pointer)
+                        .max_by_key(|p| p.elements.len())
+                } else {
+                    Some(p)
+                }
+            }
+            Type::Array(Array { elements: types }) | Type::Struct(Struct { types }) => types
+                .iter_mut()
+                .filter_map(Type::largest_pointer)
+                .max_by_key(|p| p.elements.len()),
+            Type::Union(u) => u.union_fields[u.union_variant].largest_pointer(),
+            Type::Typedef(t) => t.internal_type.largest_pointer(),
+            _ => None,
+        }
+    }
+    /// Number of elements of [`Type::largest_pointer`]
+    fn largest_pointer_len(&self) -> Option<usize> {
+        match self {
+            Type::Pointer(p) => p
+                .elements
+                .iter()
+                .filter_map(Type::largest_pointer_len)
+                .chain([p.elements.len()])
+                .max(),
+            Type::Array(Array { elements: types }) | Type::Struct(Struct { types }) => {
+                types.iter().filter_map(Type::largest_pointer_len).max()
+            }
+            Type::Union(u) => u.union_fields[u.union_variant].largest_pointer_len(),
+            Type::Typedef(t) => t.internal_type.largest_pointer_len(),
+            _ => None,
+        }
+    }
+    /// Number of bytes this value takes up in the fuzz input
+    pub(crate) fn serialized_size(&self) -> usize {
+        match self {
+            Type::Array(Array { elements: types }) | Type::Struct(Struct { types }) => {
+                types.iter().map(Type::serialized_size).sum()
+            }
+            // Pointers are prefixed with their number of elements as u16
+            Type::Pointer(p) => 2 + p.elements.iter().map(Type::serialized_size).sum::<usize>(),
+            Type::Enum(e) => e.size,
+            Type::Union(u) => u.variant_size() + u.union_fields[u.union_variant].serialized_size(),
+            Type::Typedef(t) => t.internal_type.serialized_size(),
+            Type::BasicType(b) => b.content.len(),
+            Type::OpaquePointer | Type::FunctionPointer => 0,
+        }
+    }
     pub(crate) fn has_chaining_bit(&self) -> bool {
         match self {
             Type::BasicType(_) => false,
@@ -43,20 +188,55 @@ impl Type {
     }
 }
 
-fn add_element(vec: &mut Vec<Type>, _: &HashMap<String, Type>, rng: &mut ThreadRng) {
+/// Pointer lengths that often reveal edge cases, all within the `u16` length prefix
+const BOUNDARY_LENGTHS: [usize; 5] = [0, 1, 255, 256, 4096];
+
+/// Mutation that may change the number of elements
+type ElementsMutation = fn(&mut Vec<Type>, &HashMap<String, Type>, &mut StdRng);
+
+fn add_element(vec: &mut Vec<Type>, _: &HashMap<String, Type>, rng: &mut StdRng) {
+    if vec.len() >= u16::MAX as usize {
+        return;
+    }
     if let Some(random_element) = vec.choose(rng) {
         vec.push(random_element.clone());
     }
 }
-fn swap_elements(vec: &mut Vec<Type>, _: &HashMap<String, Type>, rng: &mut ThreadRng) {
+fn remove_element(vec: &mut Vec<Type>, _: &HashMap<String, Type>, rng: &mut StdRng) {
     if !vec.is_empty() {
-        let index_0 = rng.gen_range(0..vec.len());
-        let index_1 = rng.gen_range(0..vec.len());
-        vec.swap(index_0, index_1);
+        vec.remove(rng.gen_range(0..vec.len()));
+    }
+}
+fn truncate_elements(vec: &mut Vec<Type>, _: &HashMap<String, Type>, rng: &mut StdRng) {
+    vec.truncate(rng.gen_range(0..=vec.len()));
+}
+fn clear_elements(vec: &mut Vec<Type>, _: &HashMap<String, Type>, _: &mut StdRng) {
+    vec.clear();
+}
+fn resize_to_boundary(vec: &mut Vec<Type>, _: &HashMap<String, Type>, rng: &mut StdRng) {
+    let length = *BOUNDARY_LENGTHS.choose(rng).unwrap();
+    if let Some(random_element) = vec.choose(rng).cloned() {
+        vec.resize(length, random_element);
     }
 }
-fn mutate_element(vec: &mut Vec<Type>, types: &HashMap<String, Type>, rng: &mut ThreadRng) {
-    if let Some(random_element) = vec.choose_mut(rng) {
+fn resize_elements(vec: &mut Vec<Type>, types: &HashMap<String, Type>, rng: &mut StdRng) {
+    let resize_mutation_functions = [
+        remove_element,
+        truncate_elements,
+        clear_elements,
+        resize_to_boundary,
+    ];
+    call_random_function!(resize_mutation_functions, rng, vec, types);
+}
+fn swap_elements(elements: &mut [Type], _: &HashMap<String, Type>, rng: &mut StdRng) {
+    if !elements.is_empty() {
+        let index_0 = rng.gen_range(0..elements.len());
+        let index_1 = rng.gen_range(0..elements.len());
+        elements.swap(index_0, index_1);
+    }
+}
+fn mutate_element(elements: &mut [Type], types: &HashMap<String, Type>, rng: &mut StdRng) {
+    if let Some(random_element) = elements.choose_mut(rng) {
         random_element.mutate(types, rng);
     }
 }
@@ -67,26 +247,42 @@ pub(crate) struct Array {
 }
 
 impl Array {
-    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut StdRng) {
         let array_mutation_functions = [swap_elements, mutate_element];
-        call_random_function!(array_mutation_functions, &mut self.elements, types, rng);
+        call_random_function!(array_mutation_functions, rng, &mut self.elements, types);
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Pointer {
     pub target_type_id: Option<String>,
+    /// Pointee of pointers that are declared inline instead of through a type name
+    pub inline_target: Option<Box<Type>>,
     pub elements: Vec<Type>,
 }
 
 impl Pointer {
-    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    /// Blank element of the pointee type
+    pub(crate) fn target_type<'a>(&'a self, types: &'a HashMap<String, Type>) -> &'a Type {
+        match &self.target_type_id {
+            Some(target_type_id) => &types[target_type_id],
+            None => self
+                .inline_target
+                .as_ref()
+                .expect("Pointers without target_type_id are declared inline"),
+        }
+    }
+    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut StdRng) {
         if self.elements.is_empty() {
-            self.elements
-                .push(types[self.target_type_id.as_ref().unwrap()].clone());
+            self.elements.push(self.target_type(types).clone());
         }
-        let array_mutation_functions = [add_element, swap_elements, mutate_element];
-        call_random_function!(array_mutation_functions, &mut self.elements, types, rng);
+        let array_mutation_functions: [ElementsMutation; 4] = [
+            add_element,
+            |elements, types, rng| swap_elements(elements, types, rng),
+            |elements, types, rng| mutate_element(elements, types, rng),
+            resize_elements,
+        ];
+        call_random_function!(array_mutation_functions, rng, &mut self.elements, types);
     }
 }
 
@@ -96,20 +292,62 @@ pub(crate) struct Struct {
 }
 
 impl Struct {
-    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut StdRng) {
         let array_mutation_functions = [mutate_element];
-        call_random_function!(array_mutation_functions, &mut self.types, types, rng);
+        call_random_function!(array_mutation_functions, rng, &mut self.types, types);
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Enum {
-    pub enum_variant: u32,
+    /// Sign extended value of the underlying integer
+    pub enum_variant: i64,
+    /// Size of the underlying integer in bytes
+    pub size: usize,
+    /// Declared enumerator values
+    pub constants: Vec<i64>,
+    /// Whether the constants may be OR-ed together
+    pub flags: bool,
 }
 
 impl Enum {
-    fn mutate(&mut self, _: &HashMap<String, Type>, rng: &mut ThreadRng) {
-        self.enum_variant = rng.gen();
+    /// Truncates the value to `size` bytes and sign extends it again
+    pub(crate) fn truncate(value: i64, size: usize) -> i64 {
+        let shift = 64 - 8 * size as u32;
+        (value << shift) >> shift
+    }
+    fn choose_constant(&mut self, rng: &mut StdRng) {
+        self.enum_variant = *self.constants.choose(rng).unwrap();
+    }
+    fn combine_flags(&mut self, rng: &mut StdRng) {
+        self.enum_variant = self
+            .constants
+            .iter()
+            .filter(|_| rng.gen())
+            .fold(0, |flags, flag| flags | flag);
+    }
+    fn set_boundary_value(&mut self, rng: &mut StdRng) {
+        let min = self.constants.iter().min().copied().unwrap_or(0);
+        let max = self.constants.iter().max().copied().unwrap_or(0);
+        let value = match rng.gen_range(0..6) {
+            0 => min.wrapping_sub(1),
+            1 => max.wrapping_add(1),
+            2 => -1,
+            3 => i64::MIN,
+            4 => i64::MAX,
+            _ => rng.gen(),
+        };
+        self.enum_variant = Enum::truncate(value, self.size);
+    }
+    fn mutate(&mut self, _: &HashMap<String, Type>, rng: &mut StdRng) {
+        // Mostly pick declared constants, as libraries tend to reject anything else right away
+        if self.constants.is_empty() || rng.gen_ratio(1, 8) {
+            self.set_boundary_value(rng);
+        } else if self.flags && rng.gen() {
+            self.combine_flags(rng);
+        } else {
+            self.choose_constant(rng);
+        }
     }
 }
 
@@ -120,15 +358,28 @@ pub(crate) struct Union {
 }
 
 impl Union {
-    fn change_variant(&mut self, _: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    /// Number of bytes of the union variant, the smallest unsigned integer holding every variant
+    pub(crate) fn variant_size(&self) -> usize {
+        let variants = self.union_fields.len() as u64;
+        if variants <= u8::MAX as u64 {
+            1
+        } else if variants <= u16::MAX as u64 {
+            2
+        } else if variants <= u32::MAX as u64 {
+            4
+        } else {
+            8
+        }
+    }
+    fn change_variant(&mut self, _: &HashMap<String, Type>, rng: &mut StdRng) {
         self.union_variant = rng.gen_range(0..self.union_fields.len());
     }
-    fn mutate_field(&mut self, types: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    fn mutate_field(&mut self, types: &HashMap<String, Type>, rng: &mut StdRng) {
         self.union_fields[self.union_variant].mutate(types, rng);
     }
-    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut StdRng) {
         let union_mutation_functions = [Union::change_variant, Union::mutate_field];
-        call_random_function!(union_mutation_functions, self, types, rng);
+        call_random_function!(union_mutation_functions, rng, self, types);
     }
 }
 
@@ -138,18 +389,28 @@ pub(crate) struct TypeDef {
 }
 
 impl TypeDef {
-    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    fn mutate(&mut self, types: &HashMap<String, Type>, rng: &mut StdRng) {
         self.internal_type.mutate(types, rng);
     }
 }
 
+/// How the bytes of a [`BasicType`] are interpreted by the target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Encoding {
+    Unsigned,
+    Signed,
+    Float,
+    Bool,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct BasicType {
     pub content: Vec<u8>,
+    pub encoding: Encoding,
 }
 
 impl BasicType {
-    pub fn mutate(&mut self, _: &HashMap<String, Type>, rng: &mut ThreadRng) {
+    pub fn mutate(&mut self, _: &HashMap<String, Type>, rng: &mut StdRng) {
         let byte_vec_mutation_functions = [
             byte_vec_mutator::bit_flip_mutator,
             byte_vec_mutator::byte_flip_mutator,
@@ -169,8 +430,27 @@ impl BasicType {
             byte_vec_mutator::bytes_copy_mutator,
             byte_vec_mutator::bytes_swap_mutator,
         ];
-        if !self.content.is_empty() {
-            call_random_function!(byte_vec_mutation_functions, &mut self.content, rng);
+        if self.content.is_empty() {
+            return;
+        }
+        match self.encoding {
+            Encoding::Bool => byte_vec_mutator::bool_flip_mutator(&mut self.content, rng),
+            Encoding::Float if rng.gen_ratio(1, 4) => {
+                byte_vec_mutator::interesting_set_mutator_float(&mut self.content, rng)
+            }
+            Encoding::Signed if rng.gen_ratio(1, 4) => {
+                let signed_mutation_functions = [
+                    byte_vec_mutator::boundary_set_mutator,
+                    byte_vec_mutator::negate_mutator,
+                ];
+                call_random_function!(signed_mutation_functions, rng, &mut self.content);
+            }
+            Encoding::Unsigned if rng.gen_ratio(1, 4) => {
+                byte_vec_mutator::boundary_set_mutator(&mut self.content, rng)
+            }
+            _ => {
+                call_random_function!(byte_vec_mutation_functions, rng, &mut self.content);
+            }
         }
     }
 }
@@ -180,4 +460,25 @@ pub(crate) struct Function {
     pub name: String,
     pub return_type: Type,
     pub parameter_types: Vec<Type>,
+    /// Parameter types before opaque parameters are replaced by [`Type::OpaquePointer`],
+    /// which determine the chained values a parameter can read
+    pub declared_parameter_types: Vec<Type>,
+}
+
+impl Function {
+    /// Number of decision bits this function occupies in every iteration:
+    /// one active bit, one bit for storing the return value on the chain
+    /// and one bit per parameter that may be read from the chain
+    pub(crate) fn decision_bits(&self) -> usize {
+        let return_type_bits = match &self.return_type {
+            Type::FunctionPointer => 0,
+            t => t.has_chaining_bit() as usize,
+        };
+        let parameter_bits = self
+            .parameter_types
+            .iter()
+            .filter(|t| !matches!(t, Type::OpaquePointer) && t.has_chaining_bit())
+            .count();
+        1 + return_type_bits + parameter_bits
+    }
 }
@@ -0,0 +1,256 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+use crate::function_api::FUNCTION_API_VERSION;
+
+/// Errors that can occur while loading the fuzz-driver-function-api-layout-json.
+///
+/// Every variant that refers to a part of the layout carries its JSON path,
+/// e.g. `types[12].fields[3]` or `functions[2].parameter_types[0].type`.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The `AUTO_DRIVER_FUNCTION_API_PATH` environmental variable is not set
+    MissingApiPath,
+    /// The API file could not be opened
+    Io { location: String, source: io::Error },
+    /// The API file is not valid JSON
+    Json(serde_json::Error),
+    /// The API file uses a newer layout version than this mutator supports
+    UnsupportedVersion { version: u32 },
+    /// The data model is neither LP64, ILP32 nor LLP64
+    UnknownDataModel { data_model: String },
+    /// A declaration does not match the layout schema
+    Schema { path: String, message: String },
+    /// A required field is missing
+    MissingField { path: String },
+    /// A field is not part of the layout, e.g. a misspelled `"pointee"`
+    UnknownField { path: String },
+    /// A field is present but has the wrong JSON type
+    InvalidField {
+        path: String,
+        expected: &'static str,
+    },
+    /// A type is referenced that is neither declared nor a primitive
+    UndeclaredType { path: String, name: String },
+    /// A type declaration uses an unknown `"type"` kind
+    UnknownTypeKind { path: String, kind: String },
+    /// A type name is declared more than once
+    DuplicateDeclaration { path: String, name: String },
+    /// Types contain each other by value, so they can never be resolved
+    CyclicDeclaration { path: String, chain: Vec<String> },
+    /// The declared `decision_bits_per_iteration` does not match the declared functions
+    DecisionBitsMismatch { declared: usize, computed: usize },
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::MissingApiPath => write!(
+                f,
+                "Missing AUTO_DRIVER_FUNCTION_API_PATH environmental variable that specifies the location of the fuzz-driver-function-api-layout-json"
+            ),
+            ApiError::Io { location, source } => {
+                write!(f, "Could not open {}: {}", location, source)
+            }
+            ApiError::Json(e) => write!(f, "Could not parse function API: {}", e),
+            ApiError::UnsupportedVersion { version } => write!(
+                f,
+                "Unsupported function API version {} (latest supported version is {})",
+                version, FUNCTION_API_VERSION
+            ),
+            ApiError::UnknownDataModel { data_model } => write!(
+                f,
+                "data_model: unknown data model \"{}\" (expected LP64, ILP32 or LLP64)",
+                data_model
+            ),
+            ApiError::Schema { path, message } => write!(f, "{}: {}", path, message),
+            ApiError::MissingField { path } => write!(f, "{}: missing field", path),
+            ApiError::UnknownField { path } => write!(f, "{}: unknown field", path),
+            ApiError::InvalidField { path, expected } => {
+                write!(f, "{}: expected {}", path, expected)
+            }
+            ApiError::UndeclaredType { path, name } => {
+                write!(f, "{}: undeclared type \"{}\"", path, name)
+            }
+            ApiError::UnknownTypeKind { path, kind } => {
+                write!(f, "{}: unknown type kind \"{}\"", path, kind)
+            }
+            ApiError::DuplicateDeclaration { path, name } => {
+                write!(f, "{}: type \"{}\" is already declared", path, name)
+            }
+            ApiError::CyclicDeclaration { path, chain } => {
+                write!(f, "{}: cyclic type declaration {}", path, chain.join(" -> "))
+            }
+            ApiError::DecisionBitsMismatch { declared, computed } => write!(
+                f,
+                "decision_bits_per_iteration: declared {} decision bits, but the functions require {}",
+                declared, computed
+            ),
+        }
+    }
+}
+
+impl Error for ApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ApiError::Io { source, .. } => Some(source),
+            ApiError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that can occur while deserializing a fuzz run that does not fit the function API
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeserializationError {
+    /// The input ends before all iterations, decision bits, chaining variables or arguments are read
+    UnexpectedEnd { needed: usize, available: usize },
+    /// The union variant read from the input does not select one of the union fields
+    InvalidUnionVariant { variant: usize, variants: usize },
+    /// A chaining bit is set for a parameter whose type is never read from the chain
+    UnexpectedChainingBit { function: String, parameter: usize },
+    /// More functions are active than a fuzz run can hold, at most `u16::MAX` calls
+    TooManyCalls { calls: usize },
+}
+
+impl Display for DeserializationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeserializationError::UnexpectedEnd { needed, available } => write!(
+                f,
+                "Unexpected end of input: needed {} bytes, but only {} are left",
+                needed, available
+            ),
+            DeserializationError::InvalidUnionVariant { variant, variants } => write!(
+                f,
+                "Invalid union variant {} for a union with {} fields",
+                variant, variants
+            ),
+            DeserializationError::UnexpectedChainingBit {
+                function,
+                parameter,
+            } => write!(
+                f,
+                "Parameter {} of {} can not be read from the chain",
+                parameter, function
+            ),
+            DeserializationError::TooManyCalls { calls } => write!(
+                f,
+                "{} active functions exceed the maximum of {} calls",
+                calls,
+                u16::MAX
+            ),
+        }
+    }
+}
+
+impl Error for DeserializationError {}
+
+/// Errors that can occur while encoding a hand-written fuzz run that does not fit the function API.
+///
+/// Every variant that refers to a part of the fuzz run carries its path,
+/// e.g. `calls[2].arguments[0][3]`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EncodingError {
+    /// A fuzz run can hold at most `u16::MAX` calls
+    TooManyCalls { calls: usize },
+    /// A function is called that is not declared in the function API
+    UnknownFunction { path: String, name: String },
+    /// A function is called with the wrong number of arguments
+    ArgumentCount {
+        path: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The return value is stored on the chain, but the return type has no chaining bit
+    UnexpectedChainReturn { path: String },
+    /// An argument is passed in a way its parameter does not support
+    InvalidArgument {
+        path: String,
+        expected: &'static str,
+    },
+    /// A value does not match the type it is encoded as
+    TypeMismatch {
+        path: String,
+        expected: &'static str,
+    },
+    /// An array, struct or byte value has the wrong number of elements
+    LengthMismatch {
+        path: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A pointer can hold at most `u16::MAX` elements
+    TooManyElements { path: String, elements: usize },
+    /// An integer does not fit the size of its type
+    OutOfRange { path: String },
+    /// The union variant does not select one of the union fields
+    InvalidUnionVariant {
+        path: String,
+        variant: usize,
+        variants: usize,
+    },
+}
+
+impl Display for EncodingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodingError::TooManyCalls { calls } => write!(
+                f,
+                "calls: {} calls exceed the maximum of {}",
+                calls,
+                u16::MAX
+            ),
+            EncodingError::UnknownFunction { path, name } => {
+                write!(f, "{}: unknown function \"{}\"", path, name)
+            }
+            EncodingError::ArgumentCount {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: expected {} arguments, found {}",
+                path, expected, found
+            ),
+            EncodingError::UnexpectedChainReturn { path } => {
+                write!(f, "{}: return value can not be stored on the chain", path)
+            }
+            EncodingError::InvalidArgument { path, expected }
+            | EncodingError::TypeMismatch { path, expected } => {
+                write!(f, "{}: expected {}", path, expected)
+            }
+            EncodingError::LengthMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: expected {} elements, found {}",
+                path, expected, found
+            ),
+            EncodingError::TooManyElements { path, elements } => write!(
+                f,
+                "{}: {} elements exceed the maximum of {}",
+                path,
+                elements,
+                u16::MAX
+            ),
+            EncodingError::OutOfRange { path } => {
+                write!(f, "{}: value does not fit the size of its type", path)
+            }
+            EncodingError::InvalidUnionVariant {
+                path,
+                variant,
+                variants,
+            } => write!(
+                f,
+                "{}: invalid union variant {} for a union with {} fields",
+                path, variant, variants
+            ),
+        }
+    }
+}
+
+impl Error for EncodingError {}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+use std::ptr;
+
+use log::debug;
+use rand::prelude::{SliceRandom, StdRng};
+use rand::Rng;
+
+use crate::auto_driver_mutator::{AutoDriverMutator, FunctionArgument};
+use crate::c_types::Function;
+
+/// Weight of functions that never appeared in a new queue entry
+const BASE_WEIGHT: f64 = 1.0;
+/// Share of the extra weight a function keeps with every new queue entry it does not appear in
+const WEIGHT_DECAY: f64 = 0.9;
+/// Argument values kept per parameter
+const VALUE_POOL_SIZE: usize = 16;
+
+/// What the mutator learned about a function from queue entries that found new coverage
+#[derive(Clone, Debug)]
+pub(crate) struct FunctionFeedback {
+    /// Likelihood of the function being chosen for new calls
+    weight: f64,
+    returns: u32,
+    chained_returns: u32,
+    /// Argument values of new queue entries, per parameter
+    value_pools: Vec<Vec<FunctionArgument>>,
+}
+
+impl FunctionFeedback {
+    pub(crate) fn new(function: &Function) -> Self {
+        Self {
+            weight: BASE_WEIGHT,
+            returns: 0,
+            chained_returns: 0,
+            value_pools: vec![Vec::new(); function.parameter_types.len()],
+        }
+    }
+}
+
+impl AutoDriverMutator {
+    /// Records the functions, chain decisions and argument values of a queue entry that found new coverage,
+    /// so later mutations favor them. Returns whether the entry fits the function API.
+    pub fn learn_from_queue_entry(&self, buffer: &[u8]) -> bool {
+        let called_functions = match self.try_deserialize_fuzz_run(buffer) {
+            Ok(called_functions) => called_functions,
+            Err(e) => {
+                debug!("Not learning from queue entry ({})", e);
+                return false;
+            }
+        };
+        let rng = &mut *self.rng.borrow_mut();
+        let mut feedback = self.feedback.borrow_mut();
+        let mut called = HashSet::new();
+        for function_call in called_functions.iter() {
+            let index = self.function_index(function_call.function);
+            called.insert(index);
+            let function_feedback = &mut feedback[index];
+            if let Some(chain_return) = function_call.chain_return_type {
+                function_feedback.returns += 1;
+                function_feedback.chained_returns += chain_return as u32;
+            }
+            for (argument, value_pool) in function_call
+                .arguments
+                .iter()
+                .zip(function_feedback.value_pools.iter_mut())
+            {
+                if !matches!(
+                    argument,
+                    FunctionArgument::Basic(_) | FunctionArgument::FuzzInput(_)
+                ) || value_pool.contains(argument)
+                {
+                    continue;
+                }
+                if value_pool.len() < VALUE_POOL_SIZE {
+                    value_pool.push(argument.clone());
+                } else {
+                    value_pool[rng.gen_range(0..VALUE_POOL_SIZE)] = argument.clone();
+                }
+            }
+        }
+        for (index, function_feedback) in feedback.iter_mut().enumerate() {
+            function_feedback.weight =
+                BASE_WEIGHT + (function_feedback.weight - BASE_WEIGHT) * WEIGHT_DECAY;
+            if called.contains(&index) {
+                function_feedback.weight += 1.0;
+            }
+        }
+        debug!(
+            "Learned from queue entry with {} calls to {} functions",
+            called_functions.len(),
+            called.len()
+        );
+        true
+    }
+
+    /// Chooses a function for a new call, preferring those of recent queue entries
+    pub(crate) fn choose_function(&self, rng: &mut StdRng) -> &Function {
+        let feedback = self.feedback.borrow();
+        let indices: Vec<usize> = (0..self.functions.len()).collect();
+        let index = indices
+            .choose_weighted(rng, |&index| feedback[index].weight)
+            .expect("No functions declared!");
+        &self.functions[*index]
+    }
+
+    /// Chooses whether a call stores its return value on the chain,
+    /// as often as calls to the function did in queue entries
+    pub(crate) fn choose_chain_return(&self, function: &Function, rng: &mut StdRng) -> bool {
+        let function_feedback = &self.feedback.borrow()[self.function_index(function)];
+        let chained = function_feedback.chained_returns as f64 + 1.0;
+        rng.gen_bool(chained / (function_feedback.returns as f64 + 2.0))
+    }
+
+    /// Argument value of a queue entry for the parameter, if any was recorded
+    pub(crate) fn choose_pooled_argument(
+        &self,
+        function: &Function,
+        parameter: usize,
+        rng: &mut StdRng,
+    ) -> Option<FunctionArgument> {
+        self.feedback.borrow()[self.function_index(function)].value_pools[parameter]
+            .choose(rng)
+            .cloned()
+    }
+
+    pub(crate) fn function_index(&self, function: &Function) -> usize {
+        self.functions
+            .iter()
+            .position(|f| ptr::eq(f, function))
+            .expect("Function calls refer to functions of this mutator")
+    }
+}
@@ -0,0 +1,385 @@
+use std::env;
+use std::fmt::{self, Formatter};
+use std::fs::File;
+use std::io::BufReader;
+use std::str::FromStr;
+
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::error::ApiError;
+
+/// Latest version of the fuzz-driver-function-api-layout-json understood by this crate
+pub const FUNCTION_API_VERSION: u32 = 1;
+
+/// The fuzz-driver-function-api-layout-json emitted by the AutoDriver harness generator
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunctionApi {
+    /// Layout version, files without a version are treated as version 1
+    #[serde(default = "default_version")]
+    pub version: u32,
+    /// Data model of the fuzz target, files without a data model are treated as LP64
+    #[serde(default)]
+    pub data_model: DataModel,
+    pub decision_bits_per_iteration: usize,
+    pub minimal_init_chaining_variables_size: usize,
+    pub types: Vec<TypeDeclaration>,
+    pub functions: Vec<FunctionDeclaration>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+/// C data model of the fuzz target, which determines the size of `long`, pointers and friends
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataModel {
+    /// 64-bit Linux and macOS: 8 byte `long` and pointers
+    #[default]
+    #[serde(rename = "LP64")]
+    Lp64,
+    /// 32-bit targets (`-m32`): 4 byte `int`, `long` and pointers
+    #[serde(rename = "ILP32")]
+    Ilp32,
+    /// 64-bit Windows: 4 byte `long` and 8 byte pointers
+    #[serde(rename = "LLP64")]
+    Llp64,
+}
+
+impl FromStr for DataModel {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "LP64" => Ok(DataModel::Lp64),
+            "ILP32" => Ok(DataModel::Ilp32),
+            "LLP64" => Ok(DataModel::Llp64),
+            _ => Err(ApiError::UnknownDataModel {
+                data_model: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// A named type declaration in the `types` list
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    try_from = "RawTypeDeclaration"
+)]
+pub enum TypeDeclaration {
+    Struct {
+        name: String,
+        fields: Vec<TypeReference>,
+    },
+    Enum {
+        name: String,
+        /// Size of the underlying integer type in bytes
+        #[serde(default = "default_enum_size")]
+        size: usize,
+        #[serde(default)]
+        constants: Vec<EnumConstant>,
+        /// Whether the constants are flags that may be OR-ed together
+        #[serde(default)]
+        flags: bool,
+    },
+    Union {
+        name: String,
+        fields: Vec<TypeReference>,
+    },
+    Typedef {
+        name: String,
+        underlying: TypeReference,
+    },
+    FunctionPointer {
+        name: String,
+    },
+}
+
+impl TypeDeclaration {
+    pub fn name(&self) -> &str {
+        match self {
+            TypeDeclaration::Struct { name, .. }
+            | TypeDeclaration::Enum { name, .. }
+            | TypeDeclaration::Union { name, .. }
+            | TypeDeclaration::Typedef { name, .. }
+            | TypeDeclaration::FunctionPointer { name } => name,
+        }
+    }
+}
+
+fn default_enum_size() -> usize {
+    4
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TypeKind {
+    Struct,
+    Enum,
+    Union,
+    Typedef,
+    FunctionPointer,
+}
+
+/// All fields a type declaration may have.
+/// Internally tagged enums buffer their content, which loses the JSON path of nested errors,
+/// so declarations are read into this struct first and checked against their kind afterwards.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTypeDeclaration {
+    name: String,
+    #[serde(rename = "type")]
+    kind: TypeKind,
+    fields: Option<Vec<TypeReference>>,
+    size: Option<usize>,
+    constants: Option<Vec<EnumConstant>>,
+    flags: Option<bool>,
+    underlying: Option<TypeReference>,
+}
+
+impl TryFrom<RawTypeDeclaration> for TypeDeclaration {
+    type Error = de::value::Error;
+
+    fn try_from(raw: RawTypeDeclaration) -> Result<Self, Self::Error> {
+        fn required<T>(value: Option<T>, field: &'static str) -> Result<T, de::value::Error> {
+            value.ok_or_else(|| de::Error::missing_field(field))
+        }
+        let allowed: &'static [&str] = match raw.kind {
+            TypeKind::Struct | TypeKind::Union => &["fields"],
+            TypeKind::Enum => &["size", "constants", "flags"],
+            TypeKind::Typedef => &["underlying"],
+            TypeKind::FunctionPointer => &[],
+        };
+        let present = [
+            ("fields", raw.fields.is_some()),
+            ("size", raw.size.is_some()),
+            ("constants", raw.constants.is_some()),
+            ("flags", raw.flags.is_some()),
+            ("underlying", raw.underlying.is_some()),
+        ];
+        if let Some((field, _)) = present
+            .iter()
+            .find(|(field, present)| *present && !allowed.contains(field))
+        {
+            return Err(de::Error::unknown_field(field, allowed));
+        }
+        Ok(match raw.kind {
+            TypeKind::Struct => TypeDeclaration::Struct {
+                name: raw.name,
+                fields: required(raw.fields, "fields")?,
+            },
+            TypeKind::Enum => TypeDeclaration::Enum {
+                name: raw.name,
+                size: raw.size.unwrap_or_else(default_enum_size),
+                constants: raw.constants.unwrap_or_default(),
+                flags: raw.flags.unwrap_or_default(),
+            },
+            TypeKind::Union => TypeDeclaration::Union {
+                name: raw.name,
+                fields: required(raw.fields, "fields")?,
+            },
+            TypeKind::Typedef => TypeDeclaration::Typedef {
+                name: raw.name,
+                underlying: required(raw.underlying, "underlying")?,
+            },
+            TypeKind::FunctionPointer => TypeDeclaration::FunctionPointer { name: raw.name },
+        })
+    }
+}
+
+/// A named constant of an enum declaration
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnumConstant {
+    pub name: String,
+    pub value: i64,
+}
+
+/// A use of a type: either a declared type name or an inline array or pointer declaration
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TypeReference {
+    Named(String),
+    Array {
+        array_element: Box<TypeReference>,
+        length: usize,
+    },
+    Pointer {
+        pointee: Box<TypeReference>,
+    },
+}
+
+/// Fields of an inline array or pointer declaration
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InlineTypeReference {
+    pointee: Option<Box<TypeReference>>,
+    array_element: Option<Box<TypeReference>>,
+    length: Option<usize>,
+}
+
+/// Untagged enums buffer their content like internally tagged ones,
+/// so the inline declarations are read field by field to keep the JSON path of nested errors
+impl<'de> Deserialize<'de> for TypeReference {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TypeReferenceVisitor;
+
+        impl<'de> Visitor<'de> for TypeReferenceVisitor {
+            type Value = TypeReference;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                f.write_str("a type name, an array or a pointer declaration")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(TypeReference::Named(name.to_string()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                match InlineTypeReference::deserialize(MapAccessDeserializer::new(map))? {
+                    InlineTypeReference {
+                        pointee: Some(pointee),
+                        array_element: None,
+                        length: None,
+                    } => Ok(TypeReference::Pointer { pointee }),
+                    InlineTypeReference {
+                        pointee: None,
+                        array_element: Some(array_element),
+                        length: Some(length),
+                    } => Ok(TypeReference::Array {
+                        array_element,
+                        length,
+                    }),
+                    InlineTypeReference {
+                        pointee: None,
+                        array_element: Some(_),
+                        length: None,
+                    } => Err(de::Error::missing_field("length")),
+                    InlineTypeReference {
+                        pointee: None,
+                        array_element: None,
+                        ..
+                    } => Err(de::Error::missing_field("array_element")),
+                    InlineTypeReference {
+                        pointee: Some(_), ..
+                    } => Err(de::Error::custom(
+                        "expected either a pointee or an array_element and length",
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(TypeReferenceVisitor)
+    }
+}
+
+/// A function the fuzz-driver can call
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub return_type: ReturnType,
+    pub parameter_types: Vec<ParameterDeclaration>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReturnType {
+    #[serde(rename = "type")]
+    pub type_reference: TypeReference,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParameterDeclaration {
+    #[serde(rename = "type")]
+    pub type_reference: TypeReference,
+    /// Opaque parameters are always read from the chaining variables
+    pub opaque: bool,
+}
+
+impl FunctionApi {
+    /// Reads the function API from the JSON file at `function_api_location`
+    pub fn from_file(function_api_location: &str) -> Result<Self, ApiError> {
+        let file = File::open(function_api_location).map_err(|source| ApiError::Io {
+            location: function_api_location.to_string(),
+            source,
+        })?;
+        let reader = BufReader::new(file);
+        let json: Value = serde_json::from_reader(reader).map_err(ApiError::Json)?;
+        Self::from_json(&json)
+    }
+
+    /// Reads the function API like [`FunctionApi::from_file`], taking the data model from the
+    /// `AUTO_DRIVER_DATA_MODEL` environmental variable if it is set.
+    /// The same API may be fuzzed in builds for different targets, e.g. with -m32.
+    pub fn from_file_for_target(function_api_location: &str) -> Result<Self, ApiError> {
+        let mut api = Self::from_file(function_api_location)?;
+        if let Ok(data_model) = env::var("AUTO_DRIVER_DATA_MODEL") {
+            api.data_model = data_model.parse()?;
+        }
+        Ok(api)
+    }
+
+    /// Converts a parsed JSON document into the function API.
+    ///
+    /// Unlike deserializing [`FunctionApi`] directly,
+    /// errors carry the JSON path of the offending field.
+    pub fn from_json(json: &Value) -> Result<Self, ApiError> {
+        serde_path_to_error::deserialize(json).map_err(api_error)
+    }
+}
+
+/// Turns a deserialization error into the [`ApiError`] for the JSON path it occurred at
+fn api_error(error: serde_path_to_error::Error<serde_json::Error>) -> ApiError {
+    let path = match error.path().to_string() {
+        root if root == "." => String::new(),
+        path => path,
+    };
+    let message = error.into_inner().to_string();
+    // Name between the backticks of serde's messages, e.g. "missing field `length`"
+    let quoted = |prefix: &str| {
+        message
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.split('`').next())
+            .map(str::to_string)
+    };
+    if let Some(field) = quoted("missing field `") {
+        ApiError::MissingField {
+            path: join_path(&path, &field),
+        }
+    } else if let Some(field) = quoted("unknown field `") {
+        // Unknown fields of nested objects are already part of the path,
+        // those of declarations that do not fit their kind are not
+        let path = match path.rsplit(['.', ']']).next() == Some(field.as_str()) {
+            true => path,
+            false => join_path(&path, &field),
+        };
+        ApiError::UnknownField { path }
+    } else if let Some(variant) = quoted("unknown variant `") {
+        match path.as_str() {
+            "data_model" => ApiError::UnknownDataModel {
+                data_model: variant,
+            },
+            _ => ApiError::UnknownTypeKind {
+                path,
+                kind: variant,
+            },
+        }
+    } else {
+        ApiError::Schema { path, message }
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
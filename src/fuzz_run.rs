@@ -0,0 +1,550 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::auto_driver_mutator::{AutoDriverMutator, FunctionArgument, FunctionCall};
+use crate::c_types::{Array, BasicType, Encoding, Enum, Pointer, Struct, Type, TypeDef, Union};
+use crate::error::{DeserializationError, EncodingError};
+
+/// Readable form of a serialized fuzz run, for debugging and triage.
+///
+/// Serializes to JSON through serde and prints one call per line through [`Display`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FuzzRun {
+    pub calls: Vec<Call>,
+}
+
+/// A single function call of a fuzz run
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Call {
+    pub function: String,
+    /// Whether the return value is stored on the chain, `None` if the return type has no chaining bit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_return: Option<bool>,
+    pub arguments: Vec<Argument>,
+}
+
+/// How an argument of a [`Call`] is obtained by the fuzz-driver
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Argument {
+    /// Basic type that is always read from the fuzz input
+    Basic(Value),
+    /// Value read from the fuzz input
+    FuzzInput(Value),
+    /// Value read from the chain
+    Chained,
+    /// Opaque value that is always read from the chain
+    PermanentlyChained,
+}
+
+/// A typed value of the fuzz input
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Value {
+    Bool(bool),
+    Int(i128),
+    Uint(u128),
+    Float(f64),
+    /// Raw bytes of basic types without a natural representation, e.g. `long double` or NaN
+    Bytes(Vec<u8>),
+    Enum(i64),
+    Struct(Vec<Value>),
+    Array(Vec<Value>),
+    Pointer(Vec<Value>),
+    Union {
+        variant: usize,
+        value: Box<Value>,
+    },
+    FunctionPointer,
+    OpaquePointer,
+}
+
+impl AutoDriverMutator {
+    /// Decodes a serialized fuzz run into its readable form
+    pub fn decode_fuzz_run(&self, buffer: &[u8]) -> Result<FuzzRun, DeserializationError> {
+        Ok(FuzzRun::from_function_calls(
+            &self.try_deserialize_fuzz_run(buffer)?,
+        ))
+    }
+}
+
+impl AutoDriverMutator {
+    /// Encodes a readable, e.g. hand-written, fuzz run into the byte layout consumed by the fuzz-driver
+    pub fn encode_fuzz_run(&self, fuzz_run: &FuzzRun) -> Result<Vec<u8>, EncodingError> {
+        Ok(self.serialize_fuzz_run(&self.function_calls(fuzz_run)?))
+    }
+
+    /// Checks the fuzz run against the function API and converts it to the calls the mutator works on
+    pub(crate) fn function_calls(
+        &self,
+        fuzz_run: &FuzzRun,
+    ) -> Result<Vec<FunctionCall<'_>>, EncodingError> {
+        if fuzz_run.calls.len() > u16::MAX as usize {
+            return Err(EncodingError::TooManyCalls {
+                calls: fuzz_run.calls.len(),
+            });
+        }
+        let mut called_functions = Vec::new();
+        for (index, call) in fuzz_run.calls.iter().enumerate() {
+            let path = format!("calls[{}]", index);
+            let function = self
+                .functions
+                .iter()
+                .find(|function| function.name == call.function)
+                .ok_or_else(|| EncodingError::UnknownFunction {
+                    path: format!("{}.function", path),
+                    name: call.function.clone(),
+                })?;
+            let chain_return_type = match &function.return_type {
+                Type::FunctionPointer => None,
+                t if t.has_chaining_bit() => Some(call.chain_return.unwrap_or(false)),
+                _ => None,
+            };
+            if chain_return_type.is_none() && call.chain_return == Some(true) {
+                return Err(EncodingError::UnexpectedChainReturn {
+                    path: format!("{}.chain_return", path),
+                });
+            }
+            if call.arguments.len() != function.parameter_types.len() {
+                return Err(EncodingError::ArgumentCount {
+                    path: format!("{}.arguments", path),
+                    expected: function.parameter_types.len(),
+                    found: call.arguments.len(),
+                });
+            }
+            let mut arguments = Vec::new();
+            for (parameter, (argument, parameter_type)) in call
+                .arguments
+                .iter()
+                .zip(function.parameter_types.iter())
+                .enumerate()
+            {
+                let path = format!("{}.arguments[{}]", path, parameter);
+                arguments.push(match (parameter_type, argument) {
+                    (Type::OpaquePointer, Argument::PermanentlyChained) => {
+                        FunctionArgument::PermanentlyChained
+                    }
+                    (Type::OpaquePointer, _) => {
+                        return Err(EncodingError::InvalidArgument {
+                            path,
+                            expected: "a permanently_chained argument",
+                        });
+                    }
+                    (t, Argument::Basic(value) | Argument::FuzzInput(value))
+                        if !t.has_chaining_bit() =>
+                    {
+                        match value.to_type(t, &self.types, &path)? {
+                            Type::BasicType(b) => FunctionArgument::Basic(b),
+                            _ => {
+                                return Err(EncodingError::TypeMismatch {
+                                    path,
+                                    expected: "a basic type",
+                                });
+                            }
+                        }
+                    }
+                    (t, _) if !t.has_chaining_bit() => {
+                        return Err(EncodingError::InvalidArgument {
+                            path,
+                            expected: "a basic argument",
+                        });
+                    }
+                    (t, Argument::Basic(value) | Argument::FuzzInput(value)) => {
+                        FunctionArgument::FuzzInput(value.to_type(t, &self.types, &path)?)
+                    }
+                    (t, Argument::Chained) if t.is_chainable() => FunctionArgument::Chained,
+                    (_, Argument::Chained) => {
+                        return Err(EncodingError::InvalidArgument {
+                            path,
+                            expected:
+                                "a fuzz_input argument, the type can not be read from the chain",
+                        });
+                    }
+                    (_, Argument::PermanentlyChained) => {
+                        return Err(EncodingError::InvalidArgument {
+                            path,
+                            expected: "a fuzz_input or chained argument",
+                        });
+                    }
+                });
+            }
+            called_functions.push(FunctionCall {
+                function,
+                chain_return_type,
+                arguments,
+            });
+        }
+        Ok(called_functions)
+    }
+}
+
+impl FuzzRun {
+    pub(crate) fn from_function_calls(called_functions: &[FunctionCall]) -> Self {
+        FuzzRun {
+            calls: called_functions
+                .iter()
+                .map(|function_call| {
+                    Call::from_arguments(
+                        &function_call.function.name,
+                        function_call.chain_return_type,
+                        &function_call.arguments,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Call {
+    pub(crate) fn from_arguments(
+        function: &str,
+        chain_return: Option<bool>,
+        arguments: &[FunctionArgument],
+    ) -> Self {
+        Call {
+            function: function.to_string(),
+            chain_return,
+            arguments: arguments
+                .iter()
+                .map(|argument| match argument {
+                    FunctionArgument::Basic(b) => Argument::Basic(Value::from_basic_type(b)),
+                    FunctionArgument::FuzzInput(t) => Argument::FuzzInput(Value::from_type(t)),
+                    FunctionArgument::Chained => Argument::Chained,
+                    FunctionArgument::PermanentlyChained => Argument::PermanentlyChained,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Value {
+    pub(crate) fn from_type(t: &Type) -> Self {
+        match t {
+            Type::Array(a) => Value::Array(a.elements.iter().map(Value::from_type).collect()),
+            Type::Pointer(p) => Value::Pointer(p.elements.iter().map(Value::from_type).collect()),
+            Type::OpaquePointer => Value::OpaquePointer,
+            Type::Struct(s) => Value::Struct(s.types.iter().map(Value::from_type).collect()),
+            Type::Enum(e) => Value::Enum(e.enum_variant),
+            Type::Union(u) => Value::Union {
+                variant: u.union_variant,
+                value: Box::new(Value::from_type(&u.union_fields[u.union_variant])),
+            },
+            Type::Typedef(t) => Value::from_type(&t.internal_type),
+            Type::FunctionPointer => Value::FunctionPointer,
+            Type::BasicType(b) => Value::from_basic_type(b),
+        }
+    }
+
+    pub(crate) fn from_basic_type(b: &BasicType) -> Self {
+        let content = &b.content;
+        let little_endian = || {
+            content
+                .iter()
+                .rev()
+                .fold(0u128, |value, byte| value << 8 | *byte as u128)
+        };
+        match (b.encoding, content.len()) {
+            (Encoding::Bool, 1) if content[0] <= 1 => Value::Bool(content[0] == 1),
+            (Encoding::Signed, 1 | 2 | 4 | 8 | 16) => {
+                // Sign extend from the width of the type
+                let shift = 128 - 8 * content.len();
+                Value::Int(((little_endian() as i128) << shift) >> shift)
+            }
+            (Encoding::Unsigned | Encoding::Bool, 1 | 2 | 4 | 8 | 16) => {
+                Value::Uint(little_endian())
+            }
+            (Encoding::Float, 4) => {
+                let value = f32::from_le_bytes(content[..].try_into().unwrap());
+                match value.is_finite() {
+                    true => Value::Float(value as f64),
+                    false => Value::Bytes(content.clone()),
+                }
+            }
+            (Encoding::Float, 8) => {
+                let value = f64::from_le_bytes(content[..].try_into().unwrap());
+                match value.is_finite() {
+                    true => Value::Float(value),
+                    false => Value::Bytes(content.clone()),
+                }
+            }
+            _ => Value::Bytes(content.clone()),
+        }
+    }
+}
+
+impl Value {
+    /// Converts the value into the given blank type of the function API
+    pub(crate) fn to_type(
+        &self,
+        template: &Type,
+        types: &HashMap<String, Type>,
+        path: &str,
+    ) -> Result<Type, EncodingError> {
+        fn to_types<'a>(
+            values: &[Value],
+            templates: impl Iterator<Item = &'a Type>,
+            types: &HashMap<String, Type>,
+            path: &str,
+        ) -> Result<Vec<Type>, EncodingError> {
+            values
+                .iter()
+                .zip(templates)
+                .enumerate()
+                .map(|(index, (value, template))| {
+                    value.to_type(template, types, &format!("{}[{}]", path, index))
+                })
+                .collect()
+        }
+        fn check_length(path: &str, expected: usize, found: usize) -> Result<(), EncodingError> {
+            match expected == found {
+                true => Ok(()),
+                false => Err(EncodingError::LengthMismatch {
+                    path: path.to_string(),
+                    expected,
+                    found,
+                }),
+            }
+        }
+
+        match (template, self) {
+            (Type::Typedef(t), value) => Ok(Type::Typedef(TypeDef {
+                internal_type: Box::new(value.to_type(&t.internal_type, types, path)?),
+            })),
+            (Type::Array(a), Value::Array(elements)) => {
+                check_length(path, a.elements.len(), elements.len())?;
+                Ok(Type::Array(Array {
+                    elements: to_types(elements, a.elements.iter(), types, path)?,
+                }))
+            }
+            (Type::Pointer(p), Value::Pointer(elements)) => {
+                if elements.len() > u16::MAX as usize {
+                    return Err(EncodingError::TooManyElements {
+                        path: path.to_string(),
+                        elements: elements.len(),
+                    });
+                }
+                let target_type = p.target_type(types);
+                Ok(Type::Pointer(Pointer {
+                    target_type_id: p.target_type_id.clone(),
+                    inline_target: p.inline_target.clone(),
+                    elements: to_types(elements, std::iter::repeat(target_type), types, path)?,
+                }))
+            }
+            (Type::OpaquePointer, Value::OpaquePointer) => Ok(Type::OpaquePointer),
+            (Type::Struct(s), Value::Struct(fields)) => {
+                check_length(path, s.types.len(), fields.len())?;
+                Ok(Type::Struct(Struct {
+                    types: to_types(fields, s.types.iter(), types, path)?,
+                }))
+            }
+            (Type::Enum(e), Value::Enum(variant)) => {
+                // Accept both the signed and the unsigned spelling of the underlying integer
+                let bits = 8 * e.size as u32;
+                if bits < 64 && !(-(1i64 << (bits - 1))..(1i64 << bits)).contains(variant) {
+                    return Err(EncodingError::OutOfRange {
+                        path: path.to_string(),
+                    });
+                }
+                Ok(Type::Enum(Enum {
+                    enum_variant: Enum::truncate(*variant, e.size),
+                    ..e.clone()
+                }))
+            }
+            (Type::Union(u), Value::Union { variant, value }) => {
+                if *variant >= u.union_fields.len() {
+                    return Err(EncodingError::InvalidUnionVariant {
+                        path: path.to_string(),
+                        variant: *variant,
+                        variants: u.union_fields.len(),
+                    });
+                }
+                let mut union_fields = u.union_fields.clone();
+                union_fields[*variant] =
+                    value.to_type(&u.union_fields[*variant], types, &format!("{}.value", path))?;
+                Ok(Type::Union(Union {
+                    union_variant: *variant,
+                    union_fields,
+                }))
+            }
+            (Type::FunctionPointer, Value::FunctionPointer) => Ok(Type::FunctionPointer),
+            (Type::BasicType(b), value) => Ok(Type::BasicType(value.to_basic_type(b, path)?)),
+            (template, _) => Err(EncodingError::TypeMismatch {
+                path: path.to_string(),
+                expected: match template {
+                    Type::Array(_) => "an array",
+                    Type::Pointer(_) => "a pointer",
+                    Type::OpaquePointer => "an opaque pointer",
+                    Type::Struct(_) => "a struct",
+                    Type::Enum(_) => "an enum",
+                    Type::Union(_) => "a union",
+                    Type::FunctionPointer => "a function pointer",
+                    Type::Typedef(_) | Type::BasicType(_) => unreachable!("Handled above"),
+                },
+            }),
+        }
+    }
+
+    fn to_basic_type(&self, template: &BasicType, path: &str) -> Result<BasicType, EncodingError> {
+        let size = template.content.len();
+        let bits = 8 * size as u32;
+        let out_of_range = || EncodingError::OutOfRange {
+            path: path.to_string(),
+        };
+        let content = match (template.encoding, self) {
+            (_, Value::Bytes(bytes)) => {
+                if bytes.len() != size {
+                    return Err(EncodingError::LengthMismatch {
+                        path: path.to_string(),
+                        expected: size,
+                        found: bytes.len(),
+                    });
+                }
+                bytes.clone()
+            }
+            (Encoding::Float, Value::Float(value)) => match size {
+                4 => (*value as f32).to_le_bytes().to_vec(),
+                8 => value.to_le_bytes().to_vec(),
+                _ => {
+                    return Err(EncodingError::TypeMismatch {
+                        path: path.to_string(),
+                        expected: "raw bytes",
+                    })
+                }
+            },
+            (Encoding::Signed, Value::Int(_) | Value::Uint(_) | Value::Bool(_)) => {
+                let value = match self {
+                    Value::Int(value) => *value,
+                    Value::Uint(value) => i128::try_from(*value).map_err(|_| out_of_range())?,
+                    _ => matches!(self, Value::Bool(true)) as i128,
+                };
+                let fits = match bits {
+                    0 => value == 0,
+                    128 => true,
+                    _ => (-(1i128 << (bits - 1))..(1i128 << (bits - 1))).contains(&value),
+                };
+                if !fits {
+                    return Err(out_of_range());
+                }
+                value.to_le_bytes()[..size].to_vec()
+            }
+            (
+                Encoding::Unsigned | Encoding::Bool,
+                Value::Int(_) | Value::Uint(_) | Value::Bool(_),
+            ) => {
+                let value = match self {
+                    Value::Int(value) => u128::try_from(*value).map_err(|_| out_of_range())?,
+                    Value::Uint(value) => *value,
+                    _ => matches!(self, Value::Bool(true)) as u128,
+                };
+                let fits = match bits {
+                    0 => value == 0,
+                    128 => true,
+                    _ => value < 1u128 << bits,
+                };
+                if !fits {
+                    return Err(out_of_range());
+                }
+                value.to_le_bytes()[..size].to_vec()
+            }
+            (encoding, _) => {
+                return Err(EncodingError::TypeMismatch {
+                    path: path.to_string(),
+                    expected: match encoding {
+                        Encoding::Unsigned => "an unsigned integer",
+                        Encoding::Signed => "a signed integer",
+                        Encoding::Float => "a float",
+                        Encoding::Bool => "a bool",
+                    },
+                })
+            }
+        };
+        Ok(BasicType {
+            content,
+            encoding: template.encoding,
+        })
+    }
+}
+
+impl Display for FuzzRun {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, call) in self.calls.iter().enumerate() {
+            writeln!(f, "{}: {}", index, call)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Call {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.function)?;
+        write_list(f, &self.arguments)?;
+        write!(f, ")")?;
+        if self.chain_return == Some(true) {
+            write!(f, " -> chain")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Argument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Argument::Basic(value) => write!(f, "Basic({})", value),
+            Argument::FuzzInput(value) => write!(f, "FuzzInput({})", value),
+            Argument::Chained => write!(f, "Chained"),
+            Argument::PermanentlyChained => write!(f, "PermanentlyChained"),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Uint(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Bytes(bytes) => {
+                write!(f, "bytes(")?;
+                for (index, byte) in bytes.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{:02x}", byte)?;
+                }
+                write!(f, ")")
+            }
+            Value::Enum(variant) => write!(f, "enum({})", variant),
+            Value::Struct(fields) => {
+                write!(f, "{{")?;
+                write_list(f, fields)?;
+                write!(f, "}}")
+            }
+            Value::Array(elements) => {
+                write!(f, "[")?;
+                write_list(f, elements)?;
+                write!(f, "]")
+            }
+            Value::Pointer(elements) => {
+                write!(f, "pointer[{}] [", elements.len())?;
+                write_list(f, elements)?;
+                write!(f, "]")
+            }
+            Value::Union { variant, value } => write!(f, "union#{}({})", variant, value),
+            Value::FunctionPointer => write!(f, "function_pointer"),
+            Value::OpaquePointer => write!(f, "opaque_pointer"),
+        }
+    }
+}
+
+fn write_list<T: Display>(f: &mut Formatter<'_>, list: &[T]) -> std::fmt::Result {
+    for (index, element) in list.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", element)?;
+    }
+    Ok(())
+}
@@ -1,118 +1,282 @@
-use log::debug;
 use std::collections::HashMap;
 
+use log::debug;
+
 use rand::prelude::SliceRandom;
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
 use rand::Rng;
 
-use crate::auto_driver_mutator::{AutoDriverMutator, FunctionArgument, FunctionCall};
-use crate::c_types::Type;
+use crate::auto_driver_mutator::{
+    AutoDriverMutator, FunctionArgument, FunctionCall, Mutation, MutationTarget,
+};
+use crate::c_types::{Function, Type};
 
+/// Calls a random function of the array with the given arguments, followed by the RNG
 #[macro_export]
 macro_rules! call_random_function {
-    ($arr:expr $(, $args:expr)*) => {
-        let mut rng = rand::thread_rng();
-        let index = rng.gen_range(0..$arr.len());
+    ($arr:expr, $rng:expr $(, $args:expr)*) => {
+        let index = $rng.gen_range(0..$arr.len());
         let method = $arr[index];
-        method($($args),*);
+        method($($args,)* $rng);
     };
 }
 impl AutoDriverMutator {
     pub(crate) fn mutate<'b, 's: 'b>(&'s self, called_functions: &'b mut Vec<FunctionCall<'s>>) {
-        let mut rng = rand::thread_rng();
+        let rng = &mut *self.rng.borrow_mut();
         let fuzz_functions = [
-            AutoDriverMutator::add_random_function_call,
+            AutoDriverMutator::insert_random_function_call,
+            AutoDriverMutator::move_random_function_call,
             AutoDriverMutator::remove_random_function_call,
             AutoDriverMutator::mutate_random_function_call,
         ];
-        call_random_function!(fuzz_functions, self, called_functions, &mut rng);
+        call_random_function!(fuzz_functions, rng, self, called_functions);
     }
 
-    fn add_random_function_call<'b, 's: 'b>(
+    /// Combines the fuzz run with the calls of another queue entry
+    pub(crate) fn splice<'b, 's: 'b>(
         &'s self,
         called_functions: &'b mut Vec<FunctionCall<'s>>,
-        mut rng: &mut ThreadRng,
+        donor: Vec<FunctionCall<'s>>,
     ) {
-        let function = self
-            .functions
-            .choose(&mut rng)
-            .expect("No functions declared!");
-        let mut arguments = Vec::new();
-        for parameter_type in &function.parameter_types {
-            match parameter_type {
-                Type::BasicType(b) => {
-                    arguments.push(FunctionArgument::Basic(b.clone()));
-                }
-                Type::OpaquePointer => {
-                    arguments.push(FunctionArgument::PermanentlyChained);
-                }
-                _ => {
-                    arguments.push(FunctionArgument::FuzzInput(parameter_type.clone()));
-                }
-            }
-        }
-        let chain_return_type = if function.return_type.has_chaining_bit() {
-            Some(false)
+        let rng = &mut *self.rng.borrow_mut();
+        let splice_functions = [
+            AutoDriverMutator::crossover_function_calls,
+            AutoDriverMutator::insert_donor_function_calls,
+            AutoDriverMutator::swap_function_arguments,
+        ];
+        call_random_function!(splice_functions, rng, self, called_functions, donor);
+        // The number of iterations is stored as u16
+        called_functions.truncate(u16::MAX as usize);
+    }
+
+    /// Joins a prefix of one fuzz run with a suffix of the other
+    fn crossover_function_calls<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+        mut donor: Vec<FunctionCall<'s>>,
+        rng: &mut StdRng,
+    ) {
+        self.record_mutation("splice:crossover");
+        let split = rng.gen_range(0..=called_functions.len());
+        let donor_split = rng.gen_range(0..=donor.len());
+        debug!(
+            "Crossing over at {} with donor split at {}",
+            split, donor_split
+        );
+        if rng.gen() {
+            called_functions.truncate(split);
+            called_functions.extend(donor.drain(donor_split..));
         } else {
-            None
+            donor.truncate(donor_split);
+            donor.extend(called_functions.drain(split..));
+            *called_functions = donor;
+        }
+    }
+
+    /// Inserts a block of consecutive donor calls at a random position
+    fn insert_donor_function_calls<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+        mut donor: Vec<FunctionCall<'s>>,
+        rng: &mut StdRng,
+    ) {
+        self.record_mutation("splice:insert_calls");
+        if donor.is_empty() {
+            return;
+        }
+        let start = rng.gen_range(0..donor.len());
+        let end = rng.gen_range(start + 1..=donor.len());
+        let position = rng.gen_range(0..=called_functions.len());
+        debug!(
+            "Inserting donor calls {}..{} at position {}",
+            start, end, position
+        );
+        called_functions.splice(position..position, donor.drain(start..end));
+    }
+
+    /// Replaces an argument with the one of a donor call to the same function
+    fn swap_function_arguments<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+        donor: Vec<FunctionCall<'s>>,
+        mut rng: &mut StdRng,
+    ) {
+        self.record_mutation("splice:swap_arg");
+        let Some(selected_function_call) = called_functions.choose_mut(&mut rng) else {
+            return;
         };
-        debug!("Adding function call: {:?}", function);
-        called_functions.push(FunctionCall {
-            function,
-            chain_return_type,
-            arguments,
-        });
+        let donor_calls: Vec<&FunctionCall> = donor
+            .iter()
+            .filter(|function_call| {
+                function_call.function.name == selected_function_call.function.name
+            })
+            .collect();
+        let Some(donor_call) = donor_calls.choose(&mut rng) else {
+            return;
+        };
+        let swappable_arguments: Vec<usize> = (0..donor_call.arguments.len())
+            .filter(|&i| {
+                matches!(
+                    donor_call.arguments[i],
+                    FunctionArgument::Basic(_) | FunctionArgument::FuzzInput(_)
+                )
+            })
+            .collect();
+        if let Some(&i) = swappable_arguments.choose(&mut rng) {
+            debug!(
+                "Taking argument {} of {} from donor",
+                i, selected_function_call.function.name
+            );
+            selected_function_call.arguments[i] = donor_call.arguments[i].clone();
+            self.record_argument_mutation("splice:swap_arg", i);
+        }
+    }
+
+    /// Adds a new call or duplicates an existing one, keeping insertions as likely as removals
+    fn insert_random_function_call<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+        rng: &mut StdRng,
+    ) {
+        let insertion_functions = [
+            AutoDriverMutator::add_random_function_call,
+            AutoDriverMutator::duplicate_random_function_call,
+        ];
+        call_random_function!(insertion_functions, rng, self, called_functions);
+    }
+
+    fn add_random_function_call<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+        rng: &mut StdRng,
+    ) {
+        self.record_mutation("add_call");
+        // The number of iterations is stored as u16
+        if called_functions.len() >= u16::MAX as usize {
+            return;
+        }
+        let function = self.choose_function(rng);
+        self.record_function_mutation("add_call", function);
+        let position = rng.gen_range(0..=called_functions.len());
+        debug!("Adding function call at {}: {:?}", position, function);
+        called_functions.insert(position, blank_function_call(function));
+    }
+
+    fn duplicate_random_function_call<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+        rng: &mut StdRng,
+    ) {
+        self.record_mutation("duplicate_call");
+        if called_functions.is_empty() || called_functions.len() >= u16::MAX as usize {
+            return;
+        }
+        let index = rng.gen_range(0..called_functions.len());
+        self.record_function_mutation("duplicate_call", called_functions[index].function);
+        debug!("Duplicating function call {}", index);
+        called_functions.insert(index + 1, called_functions[index].clone());
+    }
+
+    fn move_random_function_call<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+        rng: &mut StdRng,
+    ) {
+        self.record_mutation("move_call");
+        if called_functions.is_empty() {
+            return;
+        }
+        let from = rng.gen_range(0..called_functions.len());
+        let function_call = called_functions.remove(from);
+        self.record_function_mutation("move_call", function_call.function);
+        let to = rng.gen_range(0..=called_functions.len());
+        debug!("Moving function call from {} to {}", from, to);
+        called_functions.insert(to, function_call);
     }
 
     fn remove_random_function_call<'b, 's: 'b>(
         &'s self,
         called_functions: &'b mut Vec<FunctionCall<'s>>,
-        rng: &mut ThreadRng,
+        rng: &mut StdRng,
     ) {
         debug!("Removing random function call");
+        self.record_mutation("remove_call");
         if !called_functions.is_empty() {
-            called_functions.remove(rng.gen_range(0..called_functions.len()));
+            let function_call = called_functions.remove(rng.gen_range(0..called_functions.len()));
+            self.record_function_mutation("remove_call", function_call.function);
         }
     }
 
     fn mutate_random_function_call<'b, 's: 'b>(
         &'s self,
         called_functions: &'b mut Vec<FunctionCall<'s>>,
-        mut rng: &mut ThreadRng,
+        mut rng: &mut StdRng,
     ) {
         fn change_argument_type(
             argument: &mut FunctionArgument,
-            _types: &HashMap<String, Type>,
-            _rng: &mut ThreadRng,
+            (function, parameter): (&Function, usize),
+            mutator: &AutoDriverMutator,
+            _rng: &mut StdRng,
         ) {
+            let parameter_type = &function.declared_parameter_types[parameter];
             match argument {
                 FunctionArgument::Basic(_) => {}
-                FunctionArgument::FuzzInput(t) => match t {
-                    Type::Array(_) | Type::Pointer(_) | Type::FunctionPointer => {
+                FunctionArgument::FuzzInput(t) => {
+                    if t.is_chainable() && mutator.can_be_chained(parameter_type) {
                         debug!("Turning fuzz input argument {:?} into chained argument", t);
                         *argument = FunctionArgument::Chained;
+                        mutator.record_argument_mutation("chain_arg", parameter);
                     }
-                    _ => {
-                        // Other types may be complex, but they are not chained, as they have no chaining variable
-                    }
-                },
-                FunctionArgument::Chained => {} // Future option: enable revert to fuzz input
+                    // Other types may be complex, but they are not chained, as they have no chaining variable
+                    // or no function returns them
+                }
+                FunctionArgument::Chained => {
+                    debug!(
+                        "Turning chained argument into fuzz input argument {:?}",
+                        parameter_type
+                    );
+                    *argument = FunctionArgument::FuzzInput(parameter_type.clone());
+                    mutator.record_argument_mutation("unchain_arg", parameter);
+                }
                 FunctionArgument::PermanentlyChained => {}
             }
         }
         fn mutate_function_argument(
             argument: &mut FunctionArgument,
-            types: &HashMap<String, Type>,
-            rng: &mut ThreadRng,
+            (function, parameter): (&Function, usize),
+            mutator: &AutoDriverMutator,
+            rng: &mut StdRng,
         ) {
+            // Values of queue entries that found new coverage are worth trying in other calls
+            if matches!(
+                argument,
+                FunctionArgument::Basic(_) | FunctionArgument::FuzzInput(_)
+            ) && rng.gen_ratio(1, 4)
+            {
+                if let Some(pooled_argument) =
+                    mutator.choose_pooled_argument(function, parameter, rng)
+                {
+                    debug!("Reusing argument value of a queue entry");
+                    *argument = pooled_argument;
+                    mutator.record_argument_mutation("pool_arg", parameter);
+                    return;
+                }
+            }
             match argument {
                 FunctionArgument::Basic(b) => {
                     debug!("Mutating basic argument: {:?}", b);
-                    b.mutate(types, rng);
+                    b.mutate(&mutator.types, rng);
+                    mutator.record_argument_mutation("mutate_arg", parameter);
                 }
                 FunctionArgument::FuzzInput(argument) => {
                     debug!("Mutating fuzz input argument: {:?}", argument);
-                    argument.mutate(types, rng);
+                    let union_variant = argument.union_variant();
+                    argument.mutate(&mutator.types, rng);
+                    if argument.union_variant() != union_variant {
+                        mutator.record_argument_mutation("union_variant", parameter);
+                    } else {
+                        mutator.record_argument_mutation("mutate_arg", parameter);
+                    }
                 }
                 FunctionArgument::Chained => {
                     debug!("Skipping chained argument");
@@ -122,22 +286,212 @@ impl AutoDriverMutator {
                 }
             }
         }
+        self.record_mutation("mutate_call");
         if let Some(selected_function_call) = called_functions.choose_mut(&mut rng) {
             debug!("Mutating function call: {:?}", selected_function_call);
+            self.record_function_mutation("mutate_call", selected_function_call.function);
             if let Some(chain_return_type) = selected_function_call.chain_return_type.as_mut() {
-                *chain_return_type = rng.gen();
+                *chain_return_type = self.choose_chain_return(selected_function_call.function, rng);
             }
-            if let Some(selected_argument) = selected_function_call.arguments.choose_mut(&mut rng) {
+            if !selected_function_call.arguments.is_empty() {
+                let parameter = rng.gen_range(0..selected_function_call.arguments.len());
+                let selected_argument = &mut selected_function_call.arguments[parameter];
                 debug!("Mutating argument: {:?}", selected_argument);
                 let function_argument_mutation_functions =
                     [change_argument_type, mutate_function_argument];
                 call_random_function!(
                     function_argument_mutation_functions,
+                    &mut rng,
                     selected_argument,
-                    &self.types,
-                    &mut rng
+                    (selected_function_call.function, parameter),
+                    self
                 );
             }
         }
     }
+
+    /// Halves pointer contents and drops trailing calls until the serialized run fits `max_size`.
+    /// Returns whether it fits, which it cannot if even the empty run is too large.
+    pub(crate) fn shrink_to_fit<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+        max_size: usize,
+    ) -> bool {
+        let mut size = self.serialized_size(called_functions);
+        while size > max_size {
+            if called_functions.is_empty() {
+                return false;
+            }
+            let calls = called_functions.len();
+            let largest_pointer = called_functions
+                .iter_mut()
+                .flat_map(|function_call| function_call.arguments.iter_mut())
+                .filter_map(|argument| match argument {
+                    FunctionArgument::FuzzInput(t) => t.largest_pointer(),
+                    _ => None,
+                })
+                .max_by_key(|p| p.elements.len());
+            // Prefer keeping calls as long as pointer contents make up most of the input.
+            // Dropping calls from the back never leaves a chained argument without its producer.
+            match largest_pointer {
+                Some(p) if p.elements.len() > calls => {
+                    debug!(
+                        "Shrinking pointer of {} elements to fit {} bytes",
+                        p.elements.len(),
+                        max_size
+                    );
+                    let kept = p.elements.len() / 2;
+                    size -= p
+                        .elements
+                        .drain(kept..)
+                        .map(|t| t.serialized_size())
+                        .sum::<usize>();
+                }
+                _ => {
+                    debug!("Dropping last of {} calls to fit {} bytes", calls, max_size);
+                    let function_call = called_functions.pop().expect("There are calls left");
+                    size -= function_call.serialized_size() + self.header_size(calls)
+                        - self.header_size(calls - 1);
+                }
+            }
+        }
+        debug_assert_eq!(size, self.serialize_fuzz_run(called_functions).len());
+        true
+    }
+
+    /// Remembers the mutation for [`CustomMutator::describe`](custom_mutator::CustomMutator::describe)
+    pub(crate) fn record_mutation(&self, kind: &'static str) {
+        self.last_mutation.set(Mutation {
+            kind,
+            target: MutationTarget::None,
+        });
+    }
+
+    fn record_function_mutation(&self, kind: &'static str, function: &Function) {
+        self.last_mutation.set(Mutation {
+            kind,
+            target: MutationTarget::Function(self.function_index(function)),
+        });
+    }
+
+    fn record_argument_mutation(&self, kind: &'static str, parameter: usize) {
+        self.last_mutation.set(Mutation {
+            kind,
+            target: MutationTarget::Argument(parameter),
+        });
+    }
+
+    /// Whether an argument of this type can be read from the chain at all:
+    /// function pointers are provided by the fuzz-driver, other values need a function returning them
+    pub(crate) fn can_be_chained(&self, parameter_type: &Type) -> bool {
+        matches!(parameter_type, Type::FunctionPointer)
+            || self
+                .functions
+                .iter()
+                .any(|f| f.produces(parameter_type, &self.types))
+    }
+
+    /// Inserts producer calls in front of calls that would otherwise read from an empty chain
+    pub(crate) fn satisfy_chain<'b, 's: 'b>(
+        &'s self,
+        called_functions: &'b mut Vec<FunctionCall<'s>>,
+    ) {
+        // Producers may themselves read from the chain, so bound the number of insertions
+        const MAX_INSERTIONS: usize = 16;
+        let rng = &mut *self.rng.borrow_mut();
+        let mut insertions = 0;
+        let mut index = 0;
+        while index < called_functions.len() {
+            let (available, remaining) = called_functions.split_at(index);
+            let function_call = &remaining[0];
+            let producer = function_call
+                .arguments
+                .iter()
+                .zip(function_call.function.declared_parameter_types.iter())
+                .filter(|(argument, parameter_type)| {
+                    matches!(
+                        argument,
+                        FunctionArgument::Chained | FunctionArgument::PermanentlyChained
+                    ) && !matches!(parameter_type, Type::FunctionPointer)
+                        && !available
+                            .iter()
+                            .any(|c| c.pushes(parameter_type, &self.types))
+                })
+                .find_map(|(_, parameter_type)| {
+                    let producers: Vec<&Function> = self
+                        .functions
+                        .iter()
+                        .filter(|f| f.produces(parameter_type, &self.types))
+                        .collect();
+                    producers.choose(rng).copied()
+                });
+            match producer {
+                Some(producer)
+                    if insertions < MAX_INSERTIONS
+                        && called_functions.len() < u16::MAX as usize =>
+                {
+                    let position = rng.gen_range(0..=index);
+                    debug!(
+                        "Inserting producer {} at {} for call {}",
+                        producer.name, position, index
+                    );
+                    let mut producer_call = blank_function_call(producer);
+                    producer_call.chain_return_type = Some(true);
+                    called_functions.insert(position, producer_call);
+                    insertions += 1;
+                    // Check the chained arguments of the producer next
+                    index = position;
+                }
+                _ => index += 1,
+            }
+        }
+    }
+}
+
+impl Function {
+    /// Whether calls to this function can push a value the given parameter type can read from the chain.
+    /// Chained values are pointers, so they fit every pointer or array of the same resolved pointee.
+    fn produces(&self, t: &Type, types: &HashMap<String, Type>) -> bool {
+        !matches!(self.return_type, Type::FunctionPointer)
+            && self.return_type.has_chaining_bit()
+            && match (self.return_type.pointee(types), t.pointee(types)) {
+                (Some(returned), Some(read)) => returned == read,
+                _ => self.return_type == *t,
+            }
+    }
+}
+
+impl FunctionCall<'_> {
+    /// Whether this call pushes a value the given parameter type can read from the chain
+    fn pushes(&self, t: &Type, types: &HashMap<String, Type>) -> bool {
+        self.chain_return_type == Some(true) && self.function.produces(t, types)
+    }
+}
+
+/// A call with blank arguments, that does not store its return value on the chain
+fn blank_function_call(function: &Function) -> FunctionCall<'_> {
+    let mut arguments = Vec::new();
+    for parameter_type in &function.parameter_types {
+        match parameter_type {
+            Type::BasicType(b) => {
+                arguments.push(FunctionArgument::Basic(b.clone()));
+            }
+            Type::OpaquePointer => {
+                arguments.push(FunctionArgument::PermanentlyChained);
+            }
+            _ => {
+                arguments.push(FunctionArgument::FuzzInput(parameter_type.clone()));
+            }
+        }
+    }
+    let chain_return_type = if function.return_type.has_chaining_bit() {
+        Some(false)
+    } else {
+        None
+    };
+    FunctionCall {
+        function,
+        chain_return_type,
+        arguments,
+    }
 }
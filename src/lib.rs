@@ -1,5 +1,15 @@
+mod afl;
 pub mod auto_driver_mutator;
 mod byte_vec_mutator;
 mod c_types;
+pub mod error;
+mod feedback;
+pub mod function_api;
+pub mod fuzz_run;
 mod internal_mutator;
+pub mod libafl_input;
+mod libafl_mutator;
+mod libfuzzer;
+mod primitives;
 mod serialization;
+mod trim;
@@ -0,0 +1,156 @@
+#![cfg(unix)]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use libafl::inputs::Input;
+use libafl_bolts::HasLen;
+use serde::{Deserialize, Serialize};
+
+use crate::auto_driver_mutator::{AutoDriverMutator, FunctionArgument, FunctionCall};
+use crate::error::EncodingError;
+use crate::fuzz_run::{Call, FuzzRun};
+
+/// LibAFL input holding the decoded fuzz run, so mutations work on calls and values directly.
+/// It is only encoded into the byte layout of the fuzz-driver when executed, see [`AutoDriverInput::to_bytes`].
+///
+/// Inputs are stored as JSON text: LibAFL writes them with postcard, which can not tell
+/// whether the optional `chain_return` of a call was skipped.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct AutoDriverInput {
+    calls: Calls,
+}
+
+#[derive(Clone, Debug)]
+enum Calls {
+    /// Not checked against a function API yet, as passed to [`AutoDriverInput::new`] or read from JSON
+    Decoded(FuzzRun),
+    /// Calls as the mutator works on them, kept after the first mutation so that
+    /// later mutations do not check and convert the whole fuzz run again
+    Resolved(Vec<ResolvedCall>),
+}
+
+/// [`FunctionCall`] without the borrow of the mutator, the function is stored by its index
+#[derive(Clone, Debug)]
+struct ResolvedCall {
+    function: usize,
+    /// Name of the function, so the input can be written without the mutator
+    name: String,
+    chain_return_type: Option<bool>,
+    arguments: Vec<FunctionArgument>,
+}
+
+impl AutoDriverInput {
+    pub fn new(fuzz_run: FuzzRun) -> Self {
+        Self {
+            calls: Calls::Decoded(fuzz_run),
+        }
+    }
+    pub fn fuzz_run(&self) -> FuzzRun {
+        match &self.calls {
+            Calls::Decoded(fuzz_run) => fuzz_run.clone(),
+            Calls::Resolved(calls) => FuzzRun {
+                calls: calls
+                    .iter()
+                    .map(|call| {
+                        Call::from_arguments(&call.name, call.chain_return_type, &call.arguments)
+                    })
+                    .collect(),
+            },
+        }
+    }
+    /// Encodes the fuzz run into the byte layout consumed by the fuzz-driver
+    pub fn to_bytes(&self, mutator: &AutoDriverMutator) -> Result<Vec<u8>, EncodingError> {
+        Ok(mutator.serialize_fuzz_run(&self.function_calls(mutator)?))
+    }
+
+    /// Calls of the input for `mutator`, only checked against its function API if they were not resolved by it
+    pub(crate) fn function_calls<'a>(
+        &self,
+        mutator: &'a AutoDriverMutator,
+    ) -> Result<Vec<FunctionCall<'a>>, EncodingError> {
+        match &self.calls {
+            Calls::Resolved(calls)
+                if calls.iter().all(|call| {
+                    mutator
+                        .functions
+                        .get(call.function)
+                        .is_some_and(|function| function.name == call.name)
+                }) =>
+            {
+                Ok(calls
+                    .iter()
+                    .map(|call| FunctionCall {
+                        function: &mutator.functions[call.function],
+                        chain_return_type: call.chain_return_type,
+                        arguments: call.arguments.clone(),
+                    })
+                    .collect())
+            }
+            Calls::Resolved(_) => mutator.function_calls(&self.fuzz_run()),
+            Calls::Decoded(fuzz_run) => mutator.function_calls(fuzz_run),
+        }
+    }
+    pub(crate) fn set_function_calls(
+        &mut self,
+        mutator: &AutoDriverMutator,
+        called_functions: Vec<FunctionCall>,
+    ) {
+        self.calls = Calls::Resolved(
+            called_functions
+                .into_iter()
+                .map(|call| ResolvedCall {
+                    function: mutator.function_index(call.function),
+                    name: call.function.name.clone(),
+                    chain_return_type: call.chain_return_type,
+                    arguments: call.arguments,
+                })
+                .collect(),
+        );
+    }
+}
+
+impl PartialEq for AutoDriverInput {
+    fn eq(&self, other: &Self) -> bool {
+        self.fuzz_run() == other.fuzz_run()
+    }
+}
+
+impl From<FuzzRun> for AutoDriverInput {
+    fn from(fuzz_run: FuzzRun) -> Self {
+        Self::new(fuzz_run)
+    }
+}
+
+impl From<AutoDriverInput> for String {
+    fn from(input: AutoDriverInput) -> Self {
+        serde_json::to_string(&input.fuzz_run()).expect("Fuzz runs can always be written as JSON")
+    }
+}
+
+impl TryFrom<String> for AutoDriverInput {
+    type Error = serde_json::Error;
+
+    fn try_from(json: String) -> Result<Self, Self::Error> {
+        Ok(Self::new(serde_json::from_str(&json)?))
+    }
+}
+
+impl Input for AutoDriverInput {
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = DefaultHasher::new();
+        String::from(self.clone()).hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for AutoDriverInput {
+    /// Number of calls
+    fn len(&self) -> usize {
+        match &self.calls {
+            Calls::Decoded(fuzz_run) => fuzz_run.calls.len(),
+            Calls::Resolved(calls) => calls.len(),
+        }
+    }
+}
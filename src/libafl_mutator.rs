@@ -0,0 +1,64 @@
+#![cfg(unix)]
+
+use custom_mutator::CustomMutator;
+use libafl::inputs::HasBytesVec;
+use libafl::mutators::{MutationResult, Mutator};
+use libafl::state::HasMaxSize;
+use libafl::Error;
+use libafl_bolts::Named;
+
+use crate::auto_driver_mutator::AutoDriverMutator;
+use crate::libafl_input::AutoDriverInput;
+
+/// Runs the same deserialize, mutate and serialize pipeline as the AFL++ custom mutator,
+/// so LibAFL fuzzers can use it in a mutational stage or in a `tuple_list!` of mutators
+impl<I, S> Mutator<I, S> for AutoDriverMutator
+where
+    I: HasBytesVec,
+    S: HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let mutated = match self.fuzz(input.bytes_mut(), None, state.max_size()) {
+            Ok(Some(mutated)) => mutated.to_vec(),
+            Ok(None) => return Ok(MutationResult::Skipped),
+            Err(e) => return Err(Error::illegal_state(e.to_string())),
+        };
+        *input.bytes_mut() = mutated;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+/// Mutates the calls of the input directly, without going through its byte layout
+impl<S> Mutator<AutoDriverInput, S> for AutoDriverMutator
+where
+    S: HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut AutoDriverInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let mut called_functions = input
+            .function_calls(self)
+            .map_err(|e| Error::illegal_argument(e.to_string()))?;
+        AutoDriverMutator::mutate(self, &mut called_functions);
+        self.satisfy_chain(&mut called_functions);
+        if !self.shrink_to_fit(&mut called_functions, state.max_size()) {
+            return Ok(MutationResult::Skipped);
+        }
+        input.set_function_calls(self, called_functions);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for AutoDriverMutator {
+    fn name(&self) -> &str {
+        "AutoDriverMutator"
+    }
+}
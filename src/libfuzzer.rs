@@ -0,0 +1,94 @@
+#![cfg(all(unix, feature = "libfuzzer"))]
+
+use std::cell::RefCell;
+
+use custom_mutator::CustomMutator;
+
+use crate::auto_driver_mutator::AutoDriverMutator;
+
+thread_local! {
+    /// Set up on first use from the same environment as the AFL++ custom mutator
+    static MUTATOR: RefCell<Option<AutoDriverMutator>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` on the mutator, seeded with the seed libFuzzer passes for this mutation.
+/// Returns `fallback` if the mutator can not be set up, initialization is retried on the next call.
+fn with_mutator<R>(seed: u32, fallback: R, f: impl FnOnce(&mut AutoDriverMutator) -> R) -> R {
+    MUTATOR.with(|mutator| {
+        let mut mutator = mutator.borrow_mut();
+        if mutator.is_none() {
+            match AutoDriverMutator::init(seed) {
+                Ok(initialized) => *mutator = Some(initialized),
+                Err(e) => {
+                    AutoDriverMutator::handle_error(e);
+                    return fallback;
+                }
+            }
+        }
+        let mutator = mutator.as_mut().unwrap();
+        mutator.set_seed(seed as u64);
+        f(mutator)
+    })
+}
+
+/// Mutates the `size` bytes at `data` in place, the result may use up to `max_size` bytes.
+/// Returns `size` and leaves the input unchanged if the mutation is skipped.
+///
+/// # Safety
+///
+/// `data` is valid for reads and writes of the larger of `size` and `max_size` bytes
+#[no_mangle]
+pub unsafe extern "C" fn LLVMFuzzerCustomMutator(
+    data: *mut u8,
+    size: usize,
+    max_size: usize,
+    seed: u32,
+) -> usize {
+    let buffer = std::slice::from_raw_parts_mut(data, max_size.max(size));
+    with_mutator(seed, size, |mutator| {
+        let mut input = buffer[..size].to_vec();
+        match mutator.fuzz(&mut input, None, max_size) {
+            Ok(Some(mutated)) if mutated.len() <= max_size => {
+                buffer[..mutated.len()].copy_from_slice(mutated);
+                mutated.len()
+            }
+            Ok(_) => size,
+            Err(e) => {
+                AutoDriverMutator::handle_error(e);
+                size
+            }
+        }
+    })
+}
+
+/// Splices the calls of both inputs into `out`, returning 0 if the result does not fit
+///
+/// # Safety
+///
+/// `data1` and `data2` are valid for reads of `size1` and `size2` bytes
+/// and `out` is valid for writes of `max_out_size` bytes
+#[no_mangle]
+pub unsafe extern "C" fn LLVMFuzzerCustomCrossOver(
+    data1: *const u8,
+    size1: usize,
+    data2: *const u8,
+    size2: usize,
+    out: *mut u8,
+    max_out_size: usize,
+    seed: u32,
+) -> usize {
+    let buffer = std::slice::from_raw_parts(data1, size1);
+    let donor = std::slice::from_raw_parts(data2, size2);
+    let out = std::slice::from_raw_parts_mut(out, max_out_size);
+    with_mutator(seed, 0, |mutator| {
+        let mut called_functions = mutator.repair_fuzz_run(buffer);
+        mutator.splice(&mut called_functions, mutator.repair_fuzz_run(donor));
+        mutator.satisfy_chain(&mut called_functions);
+        if !mutator.shrink_to_fit(&mut called_functions, max_out_size) {
+            return 0;
+        }
+        let spliced = mutator.serialize_fuzz_run(&called_functions);
+        out[..spliced.len()].copy_from_slice(&spliced);
+        spliced.len()
+    })
+}
@@ -0,0 +1,124 @@
+use crate::c_types::{BasicType, Encoding, Type};
+use crate::function_api::DataModel;
+
+/// Size of a primitive type, which may depend on the [`DataModel`] of the target
+#[derive(Clone, Copy)]
+enum Size {
+    Fixed(usize),
+    Long,
+    Pointer,
+    LongDouble,
+    WChar,
+}
+
+impl Size {
+    fn bytes(self, data_model: DataModel) -> usize {
+        match (self, data_model) {
+            (Size::Fixed(size), _) => size,
+            (Size::Long, DataModel::Lp64) => 8,
+            (Size::Long, DataModel::Ilp32 | DataModel::Llp64) => 4,
+            (Size::Pointer, DataModel::Lp64 | DataModel::Llp64) => 8,
+            (Size::Pointer, DataModel::Ilp32) => 4,
+            (Size::LongDouble, DataModel::Lp64) => 16,
+            (Size::LongDouble, DataModel::Ilp32) => 12,
+            (Size::LongDouble, DataModel::Llp64) => 8,
+            (Size::WChar, DataModel::Lp64 | DataModel::Ilp32) => 4,
+            (Size::WChar, DataModel::Llp64) => 2,
+        }
+    }
+}
+
+/// Primitive C types that are known without a declaration in the function API.
+/// Every entry lists all spellings of the type, its size and its encoding.
+const PRIMITIVES: &[(&[&str], Size, Encoding)] = &[
+    (&["void"], Size::Fixed(0), Encoding::Unsigned),
+    (&["_Bool", "bool"], Size::Fixed(1), Encoding::Bool),
+    (&["char", "signed char"], Size::Fixed(1), Encoding::Signed),
+    (&["unsigned char"], Size::Fixed(1), Encoding::Unsigned),
+    (
+        &["short", "signed short", "short int"],
+        Size::Fixed(2),
+        Encoding::Signed,
+    ),
+    (
+        &["unsigned short", "unsigned short int"],
+        Size::Fixed(2),
+        Encoding::Unsigned,
+    ),
+    (
+        &["int", "signed int", "signed"],
+        Size::Fixed(4),
+        Encoding::Signed,
+    ),
+    (
+        &["unsigned int", "unsigned"],
+        Size::Fixed(4),
+        Encoding::Unsigned,
+    ),
+    (
+        &["long", "signed long", "long int"],
+        Size::Long,
+        Encoding::Signed,
+    ),
+    (
+        &["unsigned long", "unsigned long int"],
+        Size::Long,
+        Encoding::Unsigned,
+    ),
+    (
+        &["long long", "signed long long", "long long int"],
+        Size::Fixed(8),
+        Encoding::Signed,
+    ),
+    (
+        &["unsigned long long", "unsigned long long int"],
+        Size::Fixed(8),
+        Encoding::Unsigned,
+    ),
+    (
+        &["__int128", "__int128_t"],
+        Size::Fixed(16),
+        Encoding::Signed,
+    ),
+    (
+        &["unsigned __int128", "__uint128_t"],
+        Size::Fixed(16),
+        Encoding::Unsigned,
+    ),
+    (&["float"], Size::Fixed(4), Encoding::Float),
+    (&["double"], Size::Fixed(8), Encoding::Float),
+    (&["long double"], Size::LongDouble, Encoding::Float),
+    (&["wchar_t"], Size::WChar, Encoding::Signed),
+    (&["char16_t"], Size::Fixed(2), Encoding::Unsigned),
+    (&["char32_t"], Size::Fixed(4), Encoding::Unsigned),
+    (&["size_t", "uintptr_t"], Size::Pointer, Encoding::Unsigned),
+    (
+        &["ssize_t", "ptrdiff_t", "intptr_t"],
+        Size::Pointer,
+        Encoding::Signed,
+    ),
+    (&["int8_t"], Size::Fixed(1), Encoding::Signed),
+    (&["int16_t"], Size::Fixed(2), Encoding::Signed),
+    (&["int32_t"], Size::Fixed(4), Encoding::Signed),
+    (&["int64_t"], Size::Fixed(8), Encoding::Signed),
+    (&["uint8_t"], Size::Fixed(1), Encoding::Unsigned),
+    (&["uint16_t"], Size::Fixed(2), Encoding::Unsigned),
+    (&["uint32_t"], Size::Fixed(4), Encoding::Unsigned),
+    (&["uint64_t"], Size::Fixed(8), Encoding::Unsigned),
+];
+
+/// Returns every spelling of every primitive type together with its blank [`Type`],
+/// sized for the given data model
+pub(crate) fn primitive_types(data_model: DataModel) -> impl Iterator<Item = (String, Type)> {
+    PRIMITIVES.iter().flat_map(move |(names, size, encoding)| {
+        names.iter().map(move |name| {
+            (
+                name.to_string(),
+                Type::BasicType(BasicType {
+                    content: vec![0; size.bytes(data_model)],
+                    encoding: *encoding,
+                }),
+            )
+        })
+    })
+}
@@ -4,31 +4,78 @@ use log::trace;
 
 use crate::auto_driver_mutator::{AutoDriverMutator, FunctionArgument, FunctionCall};
 use crate::c_types::{Array, BasicType, Enum, Pointer, Struct, Type, TypeDef};
+use crate::error::DeserializationError;
 
 impl AutoDriverMutator {
+    /// Deserializes a fuzz run that is known to fit the function API, e.g. one we serialized ourselves
     pub(crate) fn deserialize_fuzz_run(&self, buffer: &[u8]) -> Vec<FunctionCall> {
+        self.try_deserialize_fuzz_run(buffer)
+            .expect("Fuzz run does not fit the function API")
+    }
+
+    /// Deserializes an arbitrary buffer, failing if it is truncated or does not fit the function API
+    pub(crate) fn try_deserialize_fuzz_run(
+        &self,
+        buffer: &[u8],
+    ) -> Result<Vec<FunctionCall<'_>>, DeserializationError> {
+        self.deserialize_fuzz_run_with(buffer, false)
+    }
+
+    /// Deserializes and serializes the buffer again.
+    /// Every input emitted by the mutator reproduces itself, trailing bytes are dropped.
+    pub fn reserialize_fuzz_run(&self, buffer: &[u8]) -> Result<Vec<u8>, DeserializationError> {
+        Ok(self.serialize_fuzz_run(&self.try_deserialize_fuzz_run(buffer)?))
+    }
+
+    /// Deserializes an arbitrary buffer into the nearest valid fuzz run.
+    ///
+    /// Keeps every iteration whose decision bits are present, clamps union variants and
+    /// pointer lengths to what the layout and the remaining input allow,
+    /// zero-fills missing argument bytes and drops trailing garbage.
+    pub(crate) fn repair_fuzz_run(&self, buffer: &[u8]) -> Vec<FunctionCall<'_>> {
+        self.deserialize_fuzz_run_with(buffer, true)
+            .expect("Repairing deserialization never fails")
+    }
+
+    fn deserialize_fuzz_run_with(
+        &self,
+        buffer: &[u8],
+        repair: bool,
+    ) -> Result<Vec<FunctionCall<'_>>, DeserializationError> {
         trace!("Starting deserialization!");
         let mut buffer_iterator = buffer.iter();
         // Deserialize number of iterations
-        let number_of_iterations = u16::from_le_bytes([
-            *buffer_iterator.next().unwrap(),
-            *buffer_iterator.next().unwrap(),
-        ]);
+        let mut number_of_iterations =
+            u16::from_le_bytes(take_bytes(&mut buffer_iterator, repair)?);
         trace!(
             "Deserialized number of iterations: {:?}",
             number_of_iterations
         );
         // Deserialize decision bits
         trace!("Deserializing decision bits!");
-        let decisions = match number_of_iterations {
+        let mut decision_bytes =
+            (self.decision_bits_per_iteration * number_of_iterations as usize).div_ceil(8);
+        if buffer_iterator.len() < decision_bytes {
+            if !repair {
+                return Err(DeserializationError::UnexpectedEnd {
+                    needed: decision_bytes,
+                    available: buffer_iterator.len(),
+                });
+            }
+            // Keep only the iterations whose decision bits are complete
+            number_of_iterations =
+                (buffer_iterator.len() * 8 / self.decision_bits_per_iteration) as u16;
+            decision_bytes =
+                (self.decision_bits_per_iteration * number_of_iterations as usize).div_ceil(8);
+            trace!("Repaired number of iterations: {}", number_of_iterations);
+        }
+        let decisions = match decision_bytes {
             0 => Vec::new(),
-            _ => bitfield_to_bool_vec(buffer_iterator.by_ref().take(
-                ((self.decision_bits_per_iteration * number_of_iterations as usize) - 1) / 8 + 1,
-            ))
-            .chunks_exact(self.decision_bits_per_iteration)
-            .take(number_of_iterations as usize)
-            .map(|chunk| chunk.to_vec())
-            .collect::<Vec<Vec<bool>>>(),
+            _ => bitfield_to_bool_vec(buffer_iterator.by_ref().take(decision_bytes))
+                .chunks_exact(self.decision_bits_per_iteration)
+                .take(number_of_iterations as usize)
+                .map(|chunk| chunk.to_vec())
+                .collect::<Vec<Vec<bool>>>(),
         };
         trace!(
             "Expecting {} decision bits in {} bytes",
@@ -40,35 +87,41 @@ impl AutoDriverMutator {
             t: &Type,
             types: &HashMap<String, Type>,
             buffer_iterator: &mut std::slice::Iter<u8>,
-        ) -> Type {
+            repair: bool,
+        ) -> Result<Type, DeserializationError> {
             trace!("Deserializing type: {:?}", t);
-            match t {
+            Ok(match t {
                 Type::Array(a) => {
                     let mut elements = Vec::new();
                     for _ in &a.elements {
-                        elements.push(deserialize_type(&a.elements[0], types, buffer_iterator));
+                        elements.push(deserialize_type(
+                            &a.elements[0],
+                            types,
+                            buffer_iterator,
+                            repair,
+                        )?);
                     }
                     Type::Array(Array { elements })
                 }
                 Type::Pointer(p) => {
                     let mut elements = Vec::new();
                     trace!("Consuming 2 bytes");
-                    let length = u16::from_le_bytes([
-                        *buffer_iterator.next().unwrap(),
-                        *buffer_iterator.next().unwrap(),
-                    ]);
+                    let length = u16::from_le_bytes(take_bytes(buffer_iterator, repair)?);
                     for _ in 0..length {
+                        if repair && buffer_iterator.len() == 0 {
+                            trace!("Clamping pointer length to {}", elements.len());
+                            break;
+                        }
                         elements.push(deserialize_type(
-                            p.elements.first().unwrap_or_else(|| {
-                                types.get(&p.target_type_id.clone().unwrap()).unwrap()
-                            }),
+                            p.target_type(types),
                             types,
                             buffer_iterator,
-                        ));
+                            repair,
+                        )?);
                     }
-                    debug_assert!(p.target_type_id.is_some() || !elements.is_empty());
                     Type::Pointer(Pointer {
                         target_type_id: p.target_type_id.clone(),
+                        inline_target: p.inline_target.clone(),
                         elements,
                     })
                 }
@@ -76,21 +129,24 @@ impl AutoDriverMutator {
                 Type::Struct(s) => {
                     let mut struct_types = Vec::new();
                     for t in &s.types {
-                        struct_types.push(deserialize_type(t, types, buffer_iterator));
+                        struct_types.push(deserialize_type(t, types, buffer_iterator, repair)?);
                     }
                     Type::Struct(Struct {
                         types: struct_types,
                     })
                 }
-                Type::Enum(_) => {
-                    trace!("Consuming 4 bytes");
-                    let enum_variant = u32::from_le_bytes([
-                        *buffer_iterator.next().unwrap(),
-                        *buffer_iterator.next().unwrap(),
-                        *buffer_iterator.next().unwrap(),
-                        *buffer_iterator.next().unwrap(),
-                    ]);
-                    Type::Enum(Enum { enum_variant })
+                Type::Enum(e) => {
+                    trace!("Consuming {} bytes", e.size);
+                    let enum_variant = match e.size {
+                        1 => i8::from_le_bytes(take_bytes(buffer_iterator, repair)?) as i64,
+                        2 => i16::from_le_bytes(take_bytes(buffer_iterator, repair)?) as i64,
+                        4 => i32::from_le_bytes(take_bytes(buffer_iterator, repair)?) as i64,
+                        _ => i64::from_le_bytes(take_bytes(buffer_iterator, repair)?),
+                    };
+                    Type::Enum(Enum {
+                        enum_variant,
+                        ..e.clone()
+                    })
                 }
                 Type::Union(u) => {
                     const U8_MAX: usize = u8::MAX as usize;
@@ -102,46 +158,42 @@ impl AutoDriverMutator {
                         0..=U8_MAX => {
                             trace!("Consuming 1 byte");
                             union.union_variant =
-                                u8::from_le_bytes([*buffer_iterator.next().unwrap()]) as usize;
+                                u8::from_le_bytes(take_bytes(buffer_iterator, repair)?) as usize;
                         }
                         0..=U16_MAX => {
                             trace!("Consuming 2 bytes");
-                            union.union_variant = u16::from_le_bytes([
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                            ]) as usize;
+                            union.union_variant =
+                                u16::from_le_bytes(take_bytes(buffer_iterator, repair)?) as usize;
                         }
                         0..=U32_MAX => {
                             trace!("Consuming 4 bytes");
-                            union.union_variant = u32::from_le_bytes([
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                            ]) as usize;
+                            union.union_variant =
+                                u32::from_le_bytes(take_bytes(buffer_iterator, repair)?) as usize;
                         }
                         0..=U64_MAX => {
                             trace!("Consuming 8 bytes");
-                            union.union_variant = u64::from_le_bytes([
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                                *buffer_iterator.next().unwrap(),
-                            ]) as usize;
+                            union.union_variant =
+                                u64::from_le_bytes(take_bytes(buffer_iterator, repair)?) as usize;
                         }
                         _ => {
                             panic!("Union too large")
                         }
                     }
+                    if union.union_variant >= u.union_fields.len() {
+                        if !repair {
+                            return Err(DeserializationError::InvalidUnionVariant {
+                                variant: union.union_variant,
+                                variants: u.union_fields.len(),
+                            });
+                        }
+                        union.union_variant = u.union_fields.len() - 1;
+                    }
                     union.union_fields[union.union_variant] = deserialize_type(
                         &u.union_fields[union.union_variant],
                         types,
                         buffer_iterator,
-                    );
+                        repair,
+                    )?;
                     Type::Union(union)
                 }
                 Type::Typedef(t) => Type::Typedef(TypeDef {
@@ -149,18 +201,30 @@ impl AutoDriverMutator {
                         &t.internal_type,
                         types,
                         buffer_iterator,
-                    )),
+                        repair,
+                    )?),
                 }),
                 Type::FunctionPointer => Type::FunctionPointer,
                 Type::BasicType(b) => {
-                    let mut content = Vec::new();
                     trace!("Consuming {} bytes", b.content.len());
-                    for _ in 0..b.content.len() {
-                        content.push(*buffer_iterator.next().unwrap());
+                    if !repair && buffer_iterator.len() < b.content.len() {
+                        return Err(DeserializationError::UnexpectedEnd {
+                            needed: b.content.len(),
+                            available: buffer_iterator.len(),
+                        });
                     }
-                    Type::BasicType(BasicType { content })
+                    let mut content: Vec<u8> = buffer_iterator
+                        .by_ref()
+                        .take(b.content.len())
+                        .copied()
+                        .collect();
+                    content.resize(b.content.len(), 0);
+                    Type::BasicType(BasicType {
+                        content,
+                        encoding: b.encoding,
+                    })
                 }
-            }
+            })
         }
 
         trace!(
@@ -179,6 +243,12 @@ impl AutoDriverMutator {
             "Skipping {} chaining variable bytes",
             self.chaining_variables_size
         );
+        if !repair && buffer_iterator.len() < self.chaining_variables_size {
+            return Err(DeserializationError::UnexpectedEnd {
+                needed: self.chaining_variables_size,
+                available: buffer_iterator.len(),
+            });
+        }
         for _ in 0..self.chaining_variables_size {
             buffer_iterator.next();
         }
@@ -186,101 +256,131 @@ impl AutoDriverMutator {
         trace!("-----------------------------------");
         trace!("Deserializing fuzz input!");
         trace!("Fuzz input bytes: {:?}", buffer_iterator.clone());
-        let called_functions = decisions
-            .iter()
-            .take(number_of_iterations as usize)
-            .map(|run| run.iter())
-            .fold(Vec::new(), |mut called_functions, mut run| {
-                for function in self.functions.iter() {
-                    trace!(
-                        "Deserializing decision bits for function {:?}",
-                        function.name
-                    );
-                    trace!("Getting 1 bit: Is function active?");
-                    let active = *run.next().unwrap();
-                    let mut function_call = FunctionCall {
-                        function,
-                        chain_return_type: if function.return_type.has_chaining_bit() {
-                            if let Type::FunctionPointer = &function.return_type {
-                                None
+        let mut called_functions = Vec::new();
+        for run in decisions.iter().take(number_of_iterations as usize) {
+            // Several functions can be active in one iteration, but the fuzz run holds at most u16::MAX calls
+            if repair && called_functions.len() >= u16::MAX as usize {
+                trace!("Clamping fuzz run to {} calls", u16::MAX);
+                break;
+            }
+            let mut run = run.iter();
+            for function in self.functions.iter() {
+                trace!(
+                    "Deserializing decision bits for function {:?}",
+                    function.name
+                );
+                trace!("Getting 1 bit: Is function active?");
+                let active = *run.next().unwrap();
+                let mut function_call = FunctionCall {
+                    function,
+                    chain_return_type: if function.return_type.has_chaining_bit() {
+                        if let Type::FunctionPointer = &function.return_type {
+                            None
+                        } else {
+                            trace!(
+                                "Getting 1 bit: Store return type {:?} on chain?",
+                                function.return_type
+                            );
+                            Some(*run.next().unwrap())
+                        }
+                    } else {
+                        None
+                    },
+                    arguments: Vec::new(),
+                };
+                for (parameter, argument) in function.parameter_types.iter().enumerate() {
+                    if let Type::OpaquePointer = argument {
+                        function_call
+                            .arguments
+                            .push(FunctionArgument::PermanentlyChained);
+                    } else if !argument.has_chaining_bit() {
+                        if active {
+                            if let Type::BasicType(b) = deserialize_type(
+                                argument,
+                                &self.types,
+                                &mut buffer_iterator,
+                                repair,
+                            )? {
+                                function_call.arguments.push(FunctionArgument::Basic(b));
                             } else {
-                                trace!(
-                                    "Getting 1 bit: Store return type {:?} on chain?",
-                                    function.return_type
-                                );
-                                Some(*run.next().unwrap())
+                                panic!("If it has no decision bit it must be a basic type!");
                             }
-                        } else {
-                            None
-                        },
-                        arguments: Vec::new(),
-                    };
-                    for argument in function.parameter_types.iter() {
-                        if let Type::OpaquePointer = argument {
-                            function_call
-                                .arguments
-                                .push(FunctionArgument::PermanentlyChained);
-                        } else if !argument.has_chaining_bit() {
-                            if active {
-                                if let Type::BasicType(b) =
-                                    deserialize_type(argument, &self.types, &mut buffer_iterator)
-                                {
-                                    function_call.arguments.push(FunctionArgument::Basic(b));
-                                } else {
-                                    panic!("If it has no decision bit it must be a basic type!");
-                                }
+                        }
+                    } else {
+                        trace!("Getting 1 bit: Argument {:?} is read from chain?", argument);
+                        let mut chaining_active = *run.next().unwrap(); // <- consumes bit
+                        if active && chaining_active && !argument.is_chainable() {
+                            if !repair {
+                                return Err(DeserializationError::UnexpectedChainingBit {
+                                    function: function.name.clone(),
+                                    parameter,
+                                });
                             }
-                        } else {
-                            trace!("Getting 1 bit: Argument {:?} is read from chain?", argument);
-                            let chaining_active = *run.next().unwrap(); // <- consumes bit
-                            if active {
-                                if chaining_active {
-                                    match argument {
-                                        Type::Array(_)
-                                        | Type::Pointer(_)
-                                        | Type::FunctionPointer => {
-                                            function_call.arguments.push(FunctionArgument::Chained);
-                                        }
-                                        _ => {
-                                            unreachable!("This type should not be chained")
-                                        }
+                            chaining_active = false;
+                        }
+                        if active {
+                            if chaining_active {
+                                function_call.arguments.push(FunctionArgument::Chained);
+                            } else {
+                                match argument {
+                                    Type::BasicType(_) => {
+                                        unreachable!("BasicTypes are handled above")
                                     }
-                                } else {
-                                    match argument {
-                                        Type::BasicType(_) => {
-                                            unreachable!("BasicTypes are handled above")
-                                        }
-                                        _ => {
-                                            function_call.arguments.push(
-                                                FunctionArgument::FuzzInput(deserialize_type(
-                                                    argument,
-                                                    &self.types,
-                                                    &mut buffer_iterator,
-                                                )),
-                                            );
-                                        }
+                                    _ => {
+                                        function_call.arguments.push(FunctionArgument::FuzzInput(
+                                            deserialize_type(
+                                                argument,
+                                                &self.types,
+                                                &mut buffer_iterator,
+                                                repair,
+                                            )?,
+                                        ));
                                     }
                                 }
                             }
                         }
                     }
-                    if active {
-                        called_functions.push(function_call);
-                    }
                 }
-                called_functions
-            });
+                if active {
+                    called_functions.push(function_call);
+                }
+            }
+        }
+        if called_functions.len() > u16::MAX as usize {
+            if !repair {
+                return Err(DeserializationError::TooManyCalls {
+                    calls: called_functions.len(),
+                });
+            }
+            called_functions.truncate(u16::MAX as usize);
+        }
         trace!("-----------------------------------");
         trace!("Deserialization complete!");
         trace!("-----------------------------------\n");
-        called_functions
+        Ok(called_functions)
+    }
+
+    /// Number of bytes [`AutoDriverMutator::serialize_fuzz_run`] produces for the fuzz run
+    pub(crate) fn serialized_size(&self, called_functions: &[FunctionCall]) -> usize {
+        self.header_size(called_functions.len())
+            + called_functions
+                .iter()
+                .map(FunctionCall::serialized_size)
+                .sum::<usize>()
+    }
+
+    /// Number of bytes in front of the fuzz input: iterations, decision bits and chaining variables
+    pub(crate) fn header_size(&self, number_of_iterations: usize) -> usize {
+        2 + (number_of_iterations * self.decision_bits_per_iteration).div_ceil(8)
+            + self.chaining_variables_size
     }
 
     pub(crate) fn serialize_fuzz_run(&self, called_functions: &Vec<FunctionCall>) -> Vec<u8> {
         trace!("Starting serialization!");
         let mut buffer = Vec::new();
         // Serialize number of iterations
-        let number_of_iterations = called_functions.len() as u16;
+        let number_of_iterations =
+            u16::try_from(called_functions.len()).expect("A fuzz run holds at most u16::MAX calls");
         buffer.extend_from_slice(&(number_of_iterations).to_le_bytes());
         trace!("Number of iterations:{:?}", number_of_iterations);
         trace!("Number of iterations bytes:{:?}", buffer);
@@ -373,7 +473,7 @@ impl AutoDriverMutator {
                     }
                 }
                 Type::Enum(e) => {
-                    buffer.extend_from_slice(&(e.enum_variant).to_le_bytes());
+                    buffer.extend_from_slice(&e.enum_variant.to_le_bytes()[..e.size]);
                 }
                 Type::Union(u) => {
                     const U8_MAX: usize = u8::MAX as usize;
@@ -438,6 +538,39 @@ impl AutoDriverMutator {
         buffer
     }
 }
+
+impl FunctionCall<'_> {
+    /// Number of fuzz input bytes of the arguments
+    pub(crate) fn serialized_size(&self) -> usize {
+        self.arguments
+            .iter()
+            .map(|argument| match argument {
+                FunctionArgument::Basic(b) => b.content.len(),
+                FunctionArgument::FuzzInput(t) => t.serialized_size(),
+                FunctionArgument::Chained | FunctionArgument::PermanentlyChained => 0,
+            })
+            .sum()
+    }
+}
+
+/// Takes the next `N` bytes from the buffer, zero-filling missing bytes when repairing
+fn take_bytes<const N: usize>(
+    buffer_iterator: &mut std::slice::Iter<u8>,
+    repair: bool,
+) -> Result<[u8; N], DeserializationError> {
+    if !repair && buffer_iterator.len() < N {
+        return Err(DeserializationError::UnexpectedEnd {
+            needed: N,
+            available: buffer_iterator.len(),
+        });
+    }
+    let mut bytes = [0; N];
+    for (byte, value) in bytes.iter_mut().zip(buffer_iterator) {
+        *byte = *value;
+    }
+    Ok(bytes)
+}
+
 fn bitfield_to_bool_vec<'a>(bytes: impl Iterator<Item = &'a u8>) -> Vec<bool> {
     let mut bits = Vec::with_capacity(bytes.size_hint().0 * 8);
     for byte in bytes {
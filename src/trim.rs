@@ -0,0 +1,120 @@
+#![cfg(unix)]
+
+use log::debug;
+
+use crate::auto_driver_mutator::{AutoDriverMutator, FunctionArgument, FunctionCall};
+use crate::c_types::Type;
+
+#[derive(Debug, Default)]
+pub(crate) struct TrimState {
+    /// Smallest fuzz run found so far that keeps the behavior of the queue entry
+    accepted: Vec<u8>,
+    /// Fuzz run of the current step
+    candidate: Vec<u8>,
+    step: usize,
+    steps: usize,
+}
+
+impl AutoDriverMutator {
+    /// Starts trimming the queue entry in `buffer` and returns the number of trimming steps.
+    /// Inputs that do not fit the function API are not trimmed.
+    pub fn init_trim(&mut self, buffer: &[u8]) -> u32 {
+        let steps = match self.try_deserialize_fuzz_run(buffer) {
+            Ok(called_functions) => count_reductions(&called_functions),
+            Err(e) => {
+                debug!(
+                    "Not trimming input that does not fit the function API ({})",
+                    e
+                );
+                0
+            }
+        };
+        debug!("Trimming {} bytes in up to {} steps", buffer.len(), steps);
+        self.trim_state = TrimState {
+            accepted: buffer.to_vec(),
+            candidate: Vec::new(),
+            step: 0,
+            steps,
+        };
+        steps.min(u32::MAX as usize) as u32
+    }
+
+    /// Returns the fuzz run with the reduction of the current step applied:
+    /// calls are dropped from the back first, then pointers are halved, arrays collapsed
+    /// and basic types zeroed
+    pub fn trim(&mut self) -> &[u8] {
+        let mut trim_state = std::mem::take(&mut self.trim_state);
+        trim_state.candidate = match self.try_deserialize_fuzz_run(&trim_state.accepted) {
+            Ok(mut called_functions) => {
+                reduce(&mut called_functions, trim_state.step);
+                self.serialize_fuzz_run(&called_functions)
+            }
+            // Nothing to trim, e.g. if trimming was not started by init_trim
+            Err(_) => trim_state.accepted.clone(),
+        };
+        self.trim_state = trim_state;
+        &self.trim_state.candidate
+    }
+
+    /// Keeps the last trimmed fuzz run if it showed the same behavior and returns the next step
+    pub fn post_trim(&mut self, success: bool) -> u32 {
+        let mut trim_state = std::mem::take(&mut self.trim_state);
+        if success {
+            trim_state.accepted = std::mem::take(&mut trim_state.candidate);
+            // The same step now applies to what came after the removed part, if anything
+            let reductions = self
+                .try_deserialize_fuzz_run(&trim_state.accepted)
+                .map_or(0, |called_functions| count_reductions(&called_functions));
+            if trim_state.step >= reductions {
+                trim_state.step = trim_state.steps;
+            }
+        } else {
+            trim_state.step += 1;
+        }
+        debug!(
+            "Trimming step {} of {}: {} bytes",
+            trim_state.step,
+            trim_state.steps,
+            trim_state.accepted.len()
+        );
+        let step = trim_state.step.min(u32::MAX as usize) as u32;
+        self.trim_state = trim_state;
+        step
+    }
+}
+
+/// Number of reductions [`reduce`] can apply to the fuzz run
+fn count_reductions(called_functions: &[FunctionCall]) -> usize {
+    let mut index = usize::MAX;
+    reduce_arguments(&mut called_functions.to_vec(), &mut index);
+    called_functions.len() + (usize::MAX - index)
+}
+
+/// Applies the `index`-th reduction: the first ones each drop a call, starting from the back,
+/// the remaining ones are counted through the arguments of all calls in order
+fn reduce(called_functions: &mut Vec<FunctionCall>, mut index: usize) {
+    if index < called_functions.len() {
+        called_functions.remove(called_functions.len() - 1 - index);
+    } else {
+        index -= called_functions.len();
+        reduce_arguments(called_functions, &mut index);
+    }
+}
+
+fn reduce_arguments(called_functions: &mut [FunctionCall], index: &mut usize) -> bool {
+    called_functions
+        .iter_mut()
+        .flat_map(|function_call| function_call.arguments.iter_mut())
+        .any(|argument| match argument {
+            FunctionArgument::Basic(b) => {
+                let mut t = Type::BasicType(b.clone());
+                let applied = t.reduce(index);
+                if let Type::BasicType(reduced) = t {
+                    *b = reduced;
+                }
+                applied
+            }
+            FunctionArgument::FuzzInput(t) => t.reduce(index),
+            FunctionArgument::Chained | FunctionArgument::PermanentlyChained => false,
+        })
+}
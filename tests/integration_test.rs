@@ -2,8 +2,15 @@ mod tests {
     use std::{env, fs};
 
     use custom_mutator::CustomMutator;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
 
     use auto_driver_mutator::auto_driver_mutator::AutoDriverMutator;
+    use auto_driver_mutator::error::{ApiError, DeserializationError, EncodingError};
+    use auto_driver_mutator::function_api::{
+        DataModel, FunctionApi, TypeDeclaration, TypeReference,
+    };
+    use auto_driver_mutator::fuzz_run::{Argument, FuzzRun, Value};
 
     #[test]
     fn auto_driver_mutator_simple_arguments() {
@@ -310,4 +317,1595 @@ mod tests {
             }
         }
     }
+
+    /// Mutator for the function API, seeded so that probabilistic assertions are reproducible
+    fn seeded_mutator(json: &serde_json::Value) -> AutoDriverMutator {
+        let mut mutator = AutoDriverMutator::new(&FunctionApi::from_json(json).unwrap()).unwrap();
+        mutator.set_seed(0);
+        mutator
+    }
+
+    fn write_function_api(name: &str, json: &str) -> String {
+        let function_api_location = env::temp_dir().join(name);
+        fs::write(&function_api_location, json).unwrap();
+        function_api_location.to_str().unwrap().to_string()
+    }
+
+    /// Fuzzes `n` times from a blank input, feeding every output back in as AFL++ does, and returns all outputs
+    fn fuzz_n(mutator: &mut AutoDriverMutator, seed: u64, n: usize) -> Vec<Vec<u8>> {
+        mutator.set_seed(seed);
+        let mut buffer = vec![0];
+        let mut outputs = Vec::with_capacity(n);
+        for _ in 0..n {
+            buffer = mutator
+                .fuzz(&mut buffer.clone(), None, usize::MAX)
+                .unwrap()
+                .unwrap()
+                .to_vec();
+            outputs.push(buffer.clone());
+        }
+        outputs
+    }
+
+    /// Linked list of union values, with pointers to pointers and a data model dependent `long`
+    fn linked_list_api() -> serde_json::Value {
+        serde_json::json!({
+            "decision_bits_per_iteration": 5,
+            "minimal_init_chaining_variables_size": 16,
+            "types": [
+                { "name": "value", "type": "union", "fields": ["int", "double", "char"] },
+                { "name": "node", "type": "struct", "fields": ["value", { "pointee": "node" }] }
+            ],
+            "functions": [
+                {
+                    "name": "create",
+                    "return_type": { "type": { "pointee": "node" } },
+                    "parameter_types": [{ "type": "value", "opaque": false }]
+                },
+                {
+                    "name": "insert",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [
+                        { "type": { "pointee": { "pointee": "char" } }, "opaque": false },
+                        { "type": "long", "opaque": false }
+                    ]
+                }
+            ]
+        })
+    }
+
+    /// `consume` reads an opaque context and a context from the chain, which only `create` provides
+    fn chain_api() -> serde_json::Value {
+        serde_json::json!({
+            "decision_bits_per_iteration": 5,
+            "minimal_init_chaining_variables_size": 8,
+            "types": [{ "name": "context", "type": "struct", "fields": ["int"] }],
+            "functions": [
+                {
+                    "name": "create",
+                    "return_type": { "type": { "pointee": "context" } },
+                    "parameter_types": [{ "type": "int", "opaque": false }]
+                },
+                {
+                    "name": "consume",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [
+                        { "type": { "pointee": "context" }, "opaque": true },
+                        { "type": { "pointee": "context" }, "opaque": false },
+                        { "type": { "pointee": "char" }, "opaque": false }
+                    ]
+                }
+            ]
+        })
+    }
+
+    /// Single `read(char *)` function, so inputs are mostly pointer elements
+    fn read_pointer_api() -> serde_json::Value {
+        serde_json::json!({
+            "decision_bits_per_iteration": 2,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [],
+            "functions": [{
+                "name": "read",
+                "return_type": { "type": "void" },
+                "parameter_types": [{ "type": { "pointee": "char" }, "opaque": false }]
+            }]
+        })
+    }
+
+    /// Encodes a `read` call for each pointer, given by its elements
+    fn read_pointer_input(mutator: &AutoDriverMutator, pointers: &[Vec<i128>]) -> Vec<u8> {
+        let calls: Vec<serde_json::Value> = pointers
+            .iter()
+            .map(|elements| {
+                let elements: Vec<serde_json::Value> = elements
+                    .iter()
+                    .map(|element| serde_json::json!({ "int": element }))
+                    .collect();
+                serde_json::json!({
+                    "function": "read",
+                    "arguments": [{ "fuzz_input": { "pointer": elements } }]
+                })
+            })
+            .collect();
+        let fuzz_run: FuzzRun =
+            serde_json::from_value(serde_json::json!({ "calls": calls })).unwrap();
+        mutator.encode_fuzz_run(&fuzz_run).unwrap()
+    }
+
+    #[test]
+    fn auto_driver_mutator_reports_api_errors() {
+        let function_api_location = write_function_api(
+            "auto_driver_mutator_api_errors.json",
+            r#"{
+                "decision_bits_per_iteration": 1,
+                "minimal_init_chaining_variables_size": 0,
+                "types": [
+                    { "name": "list", "type": "struct", "fields": ["int", "node"] }
+                ],
+                "functions": []
+            }"#,
+        );
+        let result = AutoDriverMutator::from_function_api(&function_api_location);
+        let Err(ApiError::UndeclaredType { path, name }) = result else {
+            panic!("Expected an undeclared type error");
+        };
+        assert_eq!(path, "types[0].fields[1]");
+        assert_eq!(name, "node");
+    }
+
+    #[test]
+    fn auto_driver_mutator_detects_cyclic_types() {
+        let function_api_location = write_function_api(
+            "auto_driver_mutator_cyclic_types.json",
+            r#"{
+                "decision_bits_per_iteration": 1,
+                "minimal_init_chaining_variables_size": 0,
+                "types": [
+                    { "name": "a", "type": "struct", "fields": ["int", "b"] },
+                    { "name": "b", "type": "typedef", "underlying": "a" }
+                ],
+                "functions": []
+            }"#,
+        );
+        let result = AutoDriverMutator::from_function_api(&function_api_location);
+        let Err(ApiError::CyclicDeclaration { chain, .. }) = result else {
+            panic!("Expected a cyclic declaration error");
+        };
+        assert_eq!(chain, ["a", "b", "a"]);
+    }
+
+    #[test]
+    fn auto_driver_mutator_rejects_empty_declarations() {
+        let mut json = serde_json::json!({
+            "decision_bits_per_iteration": 0,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [{ "name": "value", "type": "union", "fields": [] }],
+            "functions": []
+        });
+        let Err(ApiError::InvalidField { path, .. }) =
+            AutoDriverMutator::new(&FunctionApi::from_json(&json).unwrap())
+        else {
+            panic!("Expected an empty union error");
+        };
+        assert_eq!(path, "types[0].fields");
+
+        json["types"] = serde_json::json!([]);
+        let Err(ApiError::InvalidField { path, .. }) =
+            AutoDriverMutator::new(&FunctionApi::from_json(&json).unwrap())
+        else {
+            panic!("Expected an empty function list error");
+        };
+        assert_eq!(path, "functions");
+    }
+
+    #[test]
+    fn auto_driver_mutator_self_referential_pointer() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 2,
+            "minimal_init_chaining_variables_size": 8,
+            "types": [
+                { "name": "node", "type": "struct", "fields": ["int", { "pointee": "node" }] }
+            ],
+            "functions": [
+                {
+                    "name": "append",
+                    "return_type": { "type": "int" },
+                    "parameter_types": [{ "type": { "pointee": "node" }, "opaque": false }]
+                }
+            ]
+        });
+        let mut mutator = seeded_mutator(&json);
+        let node = |value: i128, next: Vec<Value>| {
+            Value::Struct(vec![Value::Int(value), Value::Pointer(next)])
+        };
+        let list = Value::Pointer(vec![node(1, vec![node(2, vec![node(3, vec![])])])]);
+        let fuzz_run: FuzzRun = serde_json::from_value(serde_json::json!({
+            "calls": [{ "function": "append", "arguments": [{ "fuzz_input": list }] }]
+        }))
+        .unwrap();
+        let input = mutator.encode_fuzz_run(&fuzz_run).unwrap();
+        assert_eq!(mutator.decode_fuzz_run(&input).unwrap(), fuzz_run);
+
+        // Mutated lists keep their nesting through a decode and encode round trip
+        let depth = |mut value: &Value| {
+            let mut depth = 0;
+            while let Value::Pointer(elements) = value {
+                let Some(Value::Struct(fields)) = elements.first() else {
+                    break;
+                };
+                depth += 1;
+                value = &fields[1];
+            }
+            depth
+        };
+        let mut max_depth = 0;
+        for output in fuzz_n(&mut mutator, 0, 1024) {
+            let fuzz_run = mutator.decode_fuzz_run(&output).unwrap();
+            assert_eq!(mutator.encode_fuzz_run(&fuzz_run).unwrap(), output);
+            for call in &fuzz_run.calls {
+                if let Argument::FuzzInput(list) = &call.arguments[0] {
+                    max_depth = max_depth.max(depth(list));
+                }
+            }
+        }
+        assert!(max_depth > 1, "{}", max_depth);
+    }
+
+    #[test]
+    fn function_api_round_trip() {
+        let json = r#"{
+            "decision_bits_per_iteration": 3,
+            "minimal_init_chaining_variables_size": 8,
+            "types": [
+                { "name": "buffer", "type": "typedef", "underlying": { "pointee": "char" } },
+                { "name": "mode", "type": "enum" },
+                { "name": "matrix", "type": "struct", "fields": [{ "array_element": "int", "length": 4 }] }
+            ],
+            "functions": [
+                {
+                    "name": "load",
+                    "return_type": { "type": "int" },
+                    "parameter_types": [
+                        { "type": "buffer", "opaque": false },
+                        { "type": "mode", "opaque": false }
+                    ]
+                }
+            ]
+        }"#;
+        let api: FunctionApi = serde_json::from_str(json).unwrap();
+        assert_eq!(api.version, 1);
+        assert_eq!(
+            api.types[0],
+            TypeDeclaration::Typedef {
+                name: String::from("buffer"),
+                underlying: TypeReference::Pointer {
+                    pointee: Box::new(TypeReference::Named(String::from("char")))
+                },
+            }
+        );
+        let serialized = serde_json::to_value(&api).unwrap();
+        assert_eq!(FunctionApi::from_json(&serialized).unwrap(), api);
+        assert!(AutoDriverMutator::new(&api).is_ok());
+    }
+
+    #[test]
+    fn function_api_reports_unknown_type_kind() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 1,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [{ "name": "flags", "type": "bitfield" }],
+            "functions": []
+        });
+        let Err(ApiError::UnknownTypeKind { path, kind }) = FunctionApi::from_json(&json) else {
+            panic!("Expected an unknown type kind error");
+        };
+        assert_eq!(path, "types[0].type");
+        assert_eq!(kind, "bitfield");
+    }
+
+    #[test]
+    fn function_api_reports_nested_paths() {
+        let mut json = serde_json::json!({
+            "decision_bits_per_iteration": 2,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [
+                { "name": "point", "type": "struct", "fields": ["int", { "array_element": "int" }] }
+            ],
+            "functions": [
+                {
+                    "name": "draw",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [{ "type": { "pointe": "point" }, "opaque": "no" }]
+                }
+            ]
+        });
+        // Errors are reported in the order serde reads the fields, which are sorted by name
+        let Err(ApiError::Schema { path, .. }) = FunctionApi::from_json(&json) else {
+            panic!("Expected a schema error");
+        };
+        assert_eq!(path, "functions[0].parameter_types[0].opaque");
+
+        json["functions"][0]["parameter_types"][0]["opaque"] = serde_json::json!(false);
+        let Err(ApiError::UnknownField { path }) = FunctionApi::from_json(&json) else {
+            panic!("Expected an unknown field error");
+        };
+        assert_eq!(path, "functions[0].parameter_types[0].type.pointe");
+
+        json["functions"][0]["parameter_types"][0]["type"] =
+            serde_json::json!({ "pointee": "point" });
+        let Err(ApiError::MissingField { path }) = FunctionApi::from_json(&json) else {
+            panic!("Expected a missing field error");
+        };
+        assert_eq!(path, "types[0].fields[1].length");
+
+        // Fields that do not belong to the kind of a declaration
+        json["types"][0]["fields"][1]["length"] = serde_json::json!(2);
+        json["types"][0]["size"] = serde_json::json!(4);
+        let Err(ApiError::UnknownField { path }) = FunctionApi::from_json(&json) else {
+            panic!("Expected an unknown field error");
+        };
+        assert_eq!(path, "types[0].size");
+    }
+
+    #[test]
+    fn auto_driver_mutator_checks_decision_bits() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 4,
+            "minimal_init_chaining_variables_size": 8,
+            "types": [],
+            "functions": [
+                {
+                    "name": "open",
+                    "return_type": { "type": { "pointee": "char" } },
+                    "parameter_types": [
+                        { "type": { "pointee": "char" }, "opaque": false },
+                        { "type": "int", "opaque": false },
+                        { "type": { "pointee": "char" }, "opaque": true }
+                    ]
+                }
+            ]
+        });
+        let api = FunctionApi::from_json(&json).unwrap();
+        let Err(ApiError::DecisionBitsMismatch { declared, computed }) =
+            AutoDriverMutator::new(&api)
+        else {
+            panic!("Expected a decision bits mismatch error");
+        };
+        assert_eq!(declared, 4);
+        assert_eq!(computed, 3);
+    }
+
+    #[test]
+    fn auto_driver_mutator_builtin_primitives() {
+        let mut json = serde_json::json!({
+            "decision_bits_per_iteration": 1,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [],
+            "functions": [
+                {
+                    "name": "configure",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [
+                        { "type": "long", "opaque": false },
+                        { "type": "wchar_t", "opaque": false },
+                        { "type": "long double", "opaque": false }
+                    ]
+                }
+            ]
+        });
+        // One call, followed by the contents of `long`, `wchar_t` and `long double`
+        let input = |sizes: [usize; 3]| {
+            let mut input = vec![1, 0, 0b1000_0000];
+            input.extend(vec![0xff; sizes[0]]);
+            input.push(0xfe);
+            input.extend(vec![0xff; sizes[1] - 1]);
+            input.extend(1..=sizes[2] as u8);
+            input
+        };
+        let decode = |json: &serde_json::Value, input: &[u8]| {
+            let mutator = seeded_mutator(json);
+            let fuzz_run = mutator.decode_fuzz_run(input).unwrap();
+            assert_eq!(mutator.encode_fuzz_run(&fuzz_run).unwrap(), input);
+            fuzz_run.calls[0].arguments.clone()
+        };
+        let long_double = |size: u8| Argument::Basic(Value::Bytes((1..=size).collect()));
+
+        assert_eq!(
+            decode(&json, &input([8, 4, 16])),
+            vec![
+                Argument::Basic(Value::Int(-1)),
+                Argument::Basic(Value::Int(-2)),
+                long_double(16)
+            ]
+        );
+        json["data_model"] = serde_json::json!("ILP32");
+        assert_eq!(
+            decode(&json, &input([4, 4, 12])),
+            vec![
+                Argument::Basic(Value::Int(-1)),
+                Argument::Basic(Value::Int(-2)),
+                long_double(12)
+            ]
+        );
+
+        // Mutations keep the size of types without a natural representation
+        json["data_model"] = serde_json::json!("LP64");
+        let mut mutator = seeded_mutator(&json);
+        for output in fuzz_n(&mut mutator, 0, 256) {
+            for call in mutator.decode_fuzz_run(&output).unwrap().calls {
+                assert!(
+                    matches!(&call.arguments[2], Argument::Basic(Value::Bytes(bytes)) if bytes.len() == 16)
+                );
+            }
+        }
+
+        // A declared type shadows the primitive of the same name
+        json["types"] =
+            serde_json::json!([{ "name": "wchar_t", "type": "typedef", "underlying": "char16_t" }]);
+        assert_eq!(
+            decode(&json, &input([8, 2, 16])),
+            vec![
+                Argument::Basic(Value::Int(-1)),
+                Argument::Basic(Value::Uint(0xfffe)),
+                long_double(16)
+            ]
+        );
+    }
+
+    #[test]
+    fn auto_driver_mutator_declared_types_replace_primitives() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 1,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [{ "name": "size_t", "type": "typedef", "underlying": "uint16_t" }],
+            "functions": [
+                {
+                    "name": "resize",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [{ "type": "size_t", "opaque": false }]
+                }
+            ]
+        });
+        let mutator = seeded_mutator(&json);
+        let fuzz_run = mutator
+            .decode_fuzz_run(&[1, 0, 0b1000_0000, 0x34, 0x12])
+            .unwrap();
+        assert_eq!(
+            fuzz_run.calls[0].arguments,
+            vec![Argument::Basic(Value::Uint(0x1234))]
+        );
+    }
+
+    #[test]
+    fn function_api_data_model() {
+        let mut json = serde_json::json!({
+            "data_model": "ILP32",
+            "decision_bits_per_iteration": 1,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [],
+            "functions": [
+                {
+                    "name": "seek",
+                    "return_type": { "type": "long" },
+                    "parameter_types": [{ "type": "size_t", "opaque": false }]
+                }
+            ]
+        });
+        let api = FunctionApi::from_json(&json).unwrap();
+        assert_eq!(api.data_model, DataModel::Ilp32);
+        assert!(AutoDriverMutator::new(&api).is_ok());
+
+        json["data_model"] = serde_json::json!("ILP64");
+        let Err(ApiError::UnknownDataModel { data_model }) = FunctionApi::from_json(&json) else {
+            panic!("Expected an unknown data model error");
+        };
+        assert_eq!(data_model, "ILP64");
+    }
+
+    #[test]
+    fn auto_driver_mutator_survives_corrupted_input() {
+        let mut mutator = seeded_mutator(&linked_list_api());
+        let buffer = fuzz_n(&mut mutator, 0, 256).pop().unwrap();
+        // Every input, however broken, is mutated into a fuzz run that fits the function API
+        let mut fuzz_valid = |input: &[u8]| {
+            let output = mutator
+                .fuzz(&mut input.to_vec(), None, usize::MAX)
+                .unwrap()
+                .unwrap()
+                .to_vec();
+            assert!(mutator.decode_fuzz_run(&output).is_ok(), "{:?}", input);
+        };
+        // Trailing garbage
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..64 {
+            let mut extended = buffer.clone();
+            extended.extend((0..rng.gen_range(1..64)).map(|_| rng.gen::<u8>()));
+            fuzz_valid(&extended);
+        }
+        // Truncated inputs
+        for length in 0..buffer.len() {
+            fuzz_valid(&buffer[..length]);
+        }
+        // Corrupted inputs
+        for _ in 0..1024 {
+            let mut corrupted = buffer.clone();
+            for byte in corrupted.iter_mut() {
+                if rng.gen_ratio(1, 8) {
+                    *byte = rng.gen();
+                }
+            }
+            fuzz_valid(&corrupted);
+        }
+    }
+
+    #[test]
+    fn auto_driver_mutator_caps_calls_per_fuzz_run() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 2,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [],
+            "functions": [
+                { "name": "first", "return_type": { "type": "void" }, "parameter_types": [] },
+                { "name": "second", "return_type": { "type": "void" }, "parameter_types": [] }
+            ]
+        });
+        let mut mutator = seeded_mutator(&json);
+        // Both functions are active in every one of the u16::MAX iterations
+        let mut buffer = u16::MAX.to_le_bytes().to_vec();
+        buffer.extend(vec![0xff; (2 * u16::MAX as usize).div_ceil(8)]);
+        assert_eq!(
+            mutator.decode_fuzz_run(&buffer),
+            Err(DeserializationError::TooManyCalls {
+                calls: 2 * u16::MAX as usize
+            })
+        );
+        assert!(mutator.reserialize_fuzz_run(&buffer).is_err());
+
+        // Repairing keeps u16::MAX calls, the header matches the decision bits that follow
+        let output = mutator
+            .fuzz(&mut buffer, None, usize::MAX)
+            .unwrap()
+            .unwrap()
+            .to_vec();
+        let fuzz_run = mutator.decode_fuzz_run(&output).unwrap();
+        let iterations = u16::from_le_bytes([output[0], output[1]]) as usize;
+        assert_eq!(iterations, fuzz_run.calls.len());
+        assert!(iterations <= u16::MAX as usize);
+        assert_eq!(output.len(), 2 + (2 * iterations).div_ceil(8));
+    }
+
+    #[test]
+    fn auto_driver_mutator_decodes_fuzz_run() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 3,
+            "minimal_init_chaining_variables_size": 8,
+            "types": [
+                { "name": "value", "type": "union", "fields": ["int", "double"] }
+            ],
+            "functions": [
+                {
+                    "name": "write",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [
+                        { "type": { "pointee": "char" }, "opaque": false },
+                        { "type": "short", "opaque": false },
+                        { "type": "value", "opaque": false }
+                    ]
+                }
+            ]
+        });
+        let mutator = seeded_mutator(&json);
+        let mut buffer = vec![1, 0, 0b1000_0000];
+        buffer.extend([0; 8]);
+        buffer.extend([2, 0, b'h', b'i']);
+        buffer.extend((-2i16).to_le_bytes());
+        buffer.push(1);
+        buffer.extend(0.5f64.to_le_bytes());
+
+        let fuzz_run = mutator.decode_fuzz_run(&buffer).unwrap();
+        assert_eq!(fuzz_run.calls.len(), 1);
+        assert_eq!(fuzz_run.calls[0].function, "write");
+        assert_eq!(
+            fuzz_run.calls[0].arguments,
+            vec![
+                Argument::FuzzInput(Value::Pointer(vec![
+                    Value::Int(b'h' as i128),
+                    Value::Int(b'i' as i128)
+                ])),
+                Argument::Basic(Value::Int(-2)),
+                Argument::FuzzInput(Value::Union {
+                    variant: 1,
+                    value: Box::new(Value::Float(0.5)),
+                }),
+            ]
+        );
+        assert_eq!(
+            fuzz_run.to_string(),
+            "0: write(FuzzInput(pointer[2] [104, 105]), Basic(-2), FuzzInput(union#1(0.5)))\n"
+        );
+        let json = serde_json::to_string(&fuzz_run).unwrap();
+        assert_eq!(serde_json::from_str::<FuzzRun>(&json).unwrap(), fuzz_run);
+
+        assert!(mutator
+            .decode_fuzz_run(&buffer[..buffer.len() - 1])
+            .is_err());
+    }
+
+    #[test]
+    fn auto_driver_mutator_encodes_fuzz_run() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 4,
+            "minimal_init_chaining_variables_size": 8,
+            "types": [{ "name": "png_struct", "type": "struct", "fields": ["int"] }],
+            "functions": [
+                {
+                    "name": "png_create_read_struct",
+                    "return_type": { "type": { "pointee": "png_struct" } },
+                    "parameter_types": [{ "type": { "pointee": "char" }, "opaque": false }]
+                },
+                {
+                    "name": "png_set_sig_bytes",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [
+                        { "type": { "pointee": "png_struct" }, "opaque": true },
+                        { "type": "int", "opaque": false }
+                    ]
+                }
+            ]
+        });
+        let mutator = seeded_mutator(&json);
+        let fuzz_run: FuzzRun = serde_json::from_value(serde_json::json!({
+            "calls": [
+                {
+                    "function": "png_create_read_struct",
+                    "chain_return": true,
+                    "arguments": [{ "fuzz_input": { "pointer": [{ "int": 49 }, { "int": 0 }] } }]
+                },
+                {
+                    "function": "png_set_sig_bytes",
+                    "arguments": ["permanently_chained", { "basic": { "int": 8 } }]
+                }
+            ]
+        }))
+        .unwrap();
+        let buffer = mutator.encode_fuzz_run(&fuzz_run).unwrap();
+        let mut expected = vec![2, 0, 0b1100_0001];
+        expected.extend([0; 8]);
+        expected.extend([2, 0, 49, 0]);
+        expected.extend(8i32.to_le_bytes());
+        assert_eq!(buffer, expected);
+
+        let decoded = mutator.decode_fuzz_run(&buffer).unwrap();
+        assert_eq!(decoded.calls[0], fuzz_run.calls[0]);
+        assert_eq!(decoded.calls[1].arguments, fuzz_run.calls[1].arguments);
+
+        let mut invalid = fuzz_run.clone();
+        invalid.calls[1].arguments[1] = Argument::Basic(Value::Int(1 << 40));
+        assert_eq!(
+            mutator.encode_fuzz_run(&invalid),
+            Err(EncodingError::OutOfRange {
+                path: String::from("calls[1].arguments[1]")
+            })
+        );
+        invalid.calls[0].function = String::from("png_destroy_read_struct");
+        assert!(matches!(
+            mutator.encode_fuzz_run(&invalid),
+            Err(EncodingError::UnknownFunction { .. })
+        ));
+    }
+
+    #[test]
+    fn cli_converts_and_checks_inputs() {
+        let cli = |args: &[&str]| {
+            std::process::Command::new(env!("CARGO_BIN_EXE_auto_driver"))
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        let api = write_function_api(
+            "auto_driver_cli_api.json",
+            r#"{
+                "decision_bits_per_iteration": 2,
+                "minimal_init_chaining_variables_size": 4,
+                "types": [],
+                "functions": [{
+                    "name": "fill",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [
+                        { "type": { "pointee": "char" }, "opaque": false },
+                        { "type": "int", "opaque": false }
+                    ]
+                }]
+            }"#,
+        );
+        let directory = env::temp_dir().join("auto_driver_cli_queue");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let fuzz_run = directory.join(".fuzz_run.json");
+        fs::write(
+            &fuzz_run,
+            r#"{ "calls": [{ "function": "fill", "arguments": [
+                { "fuzz_input": { "pointer": [{ "int": 65 }] } },
+                { "basic": { "int": -1 } }
+            ] }] }"#,
+        )
+        .unwrap();
+        let input = directory.join("id:000000");
+        let [api, fuzz_run, input] = [&api, fuzz_run.to_str().unwrap(), input.to_str().unwrap()];
+
+        assert!(cli(&["validate-api", api]).status.success());
+        assert!(cli(&["encode", api, fuzz_run, input]).status.success());
+        let decoded = cli(&["decode", api, input]);
+        assert!(decoded.status.success());
+        assert_eq!(
+            String::from_utf8(decoded.stdout).unwrap(),
+            "0: fill(FuzzInput(pointer[1] [65]), Basic(-1))\n"
+        );
+        assert!(cli(&["check", api, directory.to_str().unwrap()])
+            .status
+            .success());
+        assert!(cli(&["stats", api, directory.to_str().unwrap()])
+            .status
+            .success());
+
+        // The data model is taken from the same environmental variable as for the mutator
+        let ilp32 = std::process::Command::new(env!("CARGO_BIN_EXE_auto_driver"))
+            .args(["validate-api", api])
+            .env("AUTO_DRIVER_DATA_MODEL", "ILP32")
+            .output()
+            .unwrap();
+        assert!(String::from_utf8(ilp32.stdout).unwrap().contains("(Ilp32)"));
+
+        let mut with_trailing_bytes = fs::read(input).unwrap();
+        with_trailing_bytes.push(0);
+        fs::write(input, with_trailing_bytes).unwrap();
+        assert!(!cli(&["check", api, directory.to_str().unwrap()])
+            .status
+            .success());
+        assert_eq!(cli(&["decode", api]).status.code(), Some(2));
+    }
+
+    #[test]
+    fn auto_driver_mutator_splices_add_buff() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 1,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [],
+            "functions": [{
+                "name": "write",
+                "return_type": { "type": "void" },
+                "parameter_types": [{ "type": "int", "opaque": false }]
+            }]
+        });
+        let mut mutator = seeded_mutator(&json);
+        let fuzz_run = |value: i128| -> FuzzRun {
+            let call = serde_json::json!({
+                "function": "write",
+                "arguments": [{ "basic": { "int": value } }]
+            });
+            serde_json::from_value(serde_json::json!({ "calls": vec![call; 4] })).unwrap()
+        };
+        let input = mutator.encode_fuzz_run(&fuzz_run(1)).unwrap();
+        let add_buff = mutator.encode_fuzz_run(&fuzz_run(0x7777)).unwrap();
+        let spliced = (0..1000).any(|_| {
+            let mut buffer = input.clone();
+            let output = mutator
+                .fuzz(&mut buffer, Some(&add_buff), usize::MAX)
+                .unwrap()
+                .unwrap()
+                .to_vec();
+            let arguments: Vec<Argument> = mutator
+                .decode_fuzz_run(&output)
+                .unwrap()
+                .calls
+                .into_iter()
+                .flat_map(|call| call.arguments)
+                .collect();
+            arguments.contains(&Argument::Basic(Value::Int(1)))
+                && arguments.contains(&Argument::Basic(Value::Int(0x7777)))
+        });
+        assert!(spliced);
+    }
+
+    #[test]
+    fn auto_driver_mutator_is_reproducible() {
+        let mut mutator = seeded_mutator(&linked_list_api());
+        let outputs = fuzz_n(&mut mutator, 7, 256);
+        // Reseeding restarts the sequence, even after the mutator learned from earlier runs
+        assert_eq!(outputs, fuzz_n(&mut mutator, 7, 256));
+        assert_eq!(
+            outputs,
+            fuzz_n(&mut seeded_mutator(&linked_list_api()), 7, 256)
+        );
+        assert_ne!(outputs, fuzz_n(&mut mutator, 8, 256));
+    }
+
+    #[test]
+    fn auto_driver_mutator_inserts_calls_anywhere() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 2,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [],
+            "functions": [
+                { "name": "configure", "return_type": { "type": "void" }, "parameter_types": [] },
+                { "name": "consume", "return_type": { "type": "void" }, "parameter_types": [] }
+            ]
+        });
+        let mut mutator = seeded_mutator(&json);
+        let input = mutator
+            .encode_fuzz_run(
+                &serde_json::from_value(serde_json::json!({
+                    "calls": [{ "function": "consume", "arguments": [] }]
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        let mut configured_first = false;
+        for _ in 0..1000 {
+            let mut buffer = input.clone();
+            let output = mutator
+                .fuzz(&mut buffer, None, usize::MAX)
+                .unwrap()
+                .unwrap()
+                .to_vec();
+            let calls = mutator.decode_fuzz_run(&output).unwrap().calls;
+            configured_first |= calls.len() == 2 && calls[0].function == "configure";
+        }
+        assert!(configured_first);
+    }
+
+    #[test]
+    fn auto_driver_mutator_satisfies_chain() {
+        let mut mutator = seeded_mutator(&chain_api());
+        let mut chained_arguments = 0;
+        for output in fuzz_n(&mut mutator, 0, 1000) {
+            let calls = mutator.decode_fuzz_run(&output).unwrap().calls;
+            for (index, call) in calls.iter().enumerate() {
+                if call.function != "consume" {
+                    continue;
+                }
+                let created = calls[..index]
+                    .iter()
+                    .any(|c| c.function == "create" && c.chain_return == Some(true));
+                assert!(created, "consume reads from an empty chain: {:?}", calls);
+                // Nothing returns char pointers, so they are never read from the chain
+                assert_ne!(call.arguments[2], Argument::Chained);
+                chained_arguments += (call.arguments[1] == Argument::Chained) as usize;
+            }
+        }
+        assert!(chained_arguments > 0);
+    }
+
+    #[test]
+    fn auto_driver_mutator_chains_by_resolved_pointee() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 8,
+            "minimal_init_chaining_variables_size": 16,
+            "types": [
+                { "name": "context", "type": "struct", "fields": ["int"] },
+                { "name": "handle", "type": "typedef", "underlying": "context" }
+            ],
+            "functions": [
+                {
+                    "name": "open",
+                    "return_type": { "type": { "pointee": "handle" } },
+                    "parameter_types": []
+                },
+                {
+                    "name": "use",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [{ "type": { "pointee": "context" }, "opaque": false }]
+                },
+                {
+                    "name": "numbers",
+                    "return_type": { "type": { "pointee": "int" } },
+                    "parameter_types": []
+                },
+                {
+                    "name": "sum",
+                    "return_type": { "type": "void" },
+                    "parameter_types": [
+                        { "type": { "array_element": "int", "length": 4 }, "opaque": false }
+                    ]
+                }
+            ]
+        });
+        let mut mutator = seeded_mutator(&json);
+        let mut chained = std::collections::HashSet::new();
+        for output in fuzz_n(&mut mutator, 0, 2000) {
+            for call in mutator.decode_fuzz_run(&output).unwrap().calls {
+                if call.arguments.first() == Some(&Argument::Chained) {
+                    chained.insert(call.function);
+                }
+            }
+        }
+        assert!(chained.contains("use"), "{:?}", chained);
+        assert!(chained.contains("sum"), "{:?}", chained);
+    }
+
+    #[test]
+    fn auto_driver_mutator_reverts_chained_arguments() {
+        let mut mutator = seeded_mutator(&chain_api());
+        let input = mutator
+            .encode_fuzz_run(
+                &serde_json::from_value(serde_json::json!({
+                    "calls": [
+                        { "function": "create", "chain_return": true, "arguments": [{ "basic": { "int": 0 } }] },
+                        {
+                            "function": "consume",
+                            "arguments": ["permanently_chained", "chained", { "fuzz_input": { "pointer": [] } }]
+                        }
+                    ]
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        let mut reverted = 0;
+        for _ in 0..1000 {
+            let output = mutator
+                .fuzz(&mut input.clone(), None, usize::MAX)
+                .unwrap()
+                .unwrap()
+                .to_vec();
+            let calls = mutator.decode_fuzz_run(&output).unwrap().calls;
+            let Some(consume) = calls.iter().find(|call| call.function == "consume") else {
+                continue;
+            };
+            // Opaque arguments are never turned into fuzz input
+            assert_eq!(consume.arguments[0], Argument::PermanentlyChained);
+            reverted +=
+                (consume.arguments[1] == Argument::FuzzInput(Value::Pointer(vec![]))) as usize;
+        }
+        assert!(reverted > 0);
+    }
+
+    #[test]
+    fn auto_driver_mutator_enum_constants() {
+        let json = serde_json::json!({
+            "decision_bits_per_iteration": 2,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [{
+                "name": "mode",
+                "type": "enum",
+                "size": 1,
+                "flags": true,
+                "constants": [
+                    { "name": "MODE_READ", "value": 1 },
+                    { "name": "MODE_WRITE", "value": 2 },
+                    { "name": "MODE_APPEND", "value": 4 }
+                ]
+            }],
+            "functions": [{
+                "name": "open",
+                "return_type": { "type": "void" },
+                "parameter_types": [{ "type": "mode", "opaque": false }]
+            }]
+        });
+        let mut mutator = seeded_mutator(&json);
+        let fuzz_run: FuzzRun = serde_json::from_value(serde_json::json!({
+            "calls": [{ "function": "open", "arguments": [{ "fuzz_input": { "enum": 255 } }] }]
+        }))
+        .unwrap();
+        let buffer = mutator.encode_fuzz_run(&fuzz_run).unwrap();
+        assert_eq!(buffer, vec![1, 0, 0b1000_0000, 0xff]);
+        assert_eq!(
+            mutator.decode_fuzz_run(&buffer).unwrap().calls[0].arguments,
+            vec![Argument::FuzzInput(Value::Enum(-1))]
+        );
+
+        // Mostly declared constants or combinations of declared flags
+        let (mut declared, mut total) = (0, 0);
+        for output in fuzz_n(&mut mutator, 0, 1000) {
+            for call in mutator.decode_fuzz_run(&output).unwrap().calls {
+                let Argument::FuzzInput(Value::Enum(mode)) = call.arguments[0] else {
+                    panic!("Expected an enum argument");
+                };
+                total += 1;
+                declared += (mode & !0b111 == 0) as usize;
+            }
+        }
+        assert!(declared * 4 > total * 3, "{} of {}", declared, total);
+
+        let mut invalid_size = json.clone();
+        invalid_size["types"][0]["size"] = serde_json::json!(3);
+        let Err(ApiError::InvalidField { path, .. }) =
+            AutoDriverMutator::new(&FunctionApi::from_json(&invalid_size).unwrap())
+        else {
+            panic!("Expected an invalid enum size");
+        };
+        assert_eq!(path, "types[0].size");
+    }
+
+    #[test]
+    fn auto_driver_mutator_resizes_pointers() {
+        let mut mutator = seeded_mutator(&read_pointer_api());
+        let short = read_pointer_input(&mutator, &[vec![7; 2]]);
+        let long = read_pointer_input(&mutator, &[vec![7; 300]]);
+        let mut lengths = std::collections::HashSet::new();
+        for i in 0..4000 {
+            let mut buffer = if i % 2 == 0 {
+                short.clone()
+            } else {
+                long.clone()
+            };
+            let output = mutator
+                .fuzz(&mut buffer, None, usize::MAX)
+                .unwrap()
+                .unwrap()
+                .to_vec();
+            let calls = mutator.decode_fuzz_run(&output).unwrap().calls;
+            if let Some(Argument::FuzzInput(Value::Pointer(elements))) = calls
+                .iter()
+                .find(|call| call.function == "read")
+                .map(|call| &call.arguments[0])
+            {
+                lengths.insert(elements.len());
+            }
+        }
+        for length in [0, 1, 255, 256, 4096] {
+            assert!(lengths.contains(&length), "{} not in {:?}", length, lengths);
+        }
+        assert!(lengths.iter().any(|&length| length > 2 && length < 299));
+    }
+
+    #[test]
+    fn auto_driver_mutator_respects_max_size() {
+        let json = read_pointer_api();
+        let mut mutator = seeded_mutator(&json);
+        let input = read_pointer_input(&mutator, &vec![vec![7; 300]; 40]);
+        for max_size in [8, 64, 1000, input.len()] {
+            for _ in 0..20 {
+                let mut buffer = input.clone();
+                let output = mutator
+                    .fuzz(&mut buffer, None, max_size)
+                    .unwrap()
+                    .expect("Mutation should be shrunk to fit max_size")
+                    .to_vec();
+                assert!(output.len() <= max_size);
+                assert!(mutator.decode_fuzz_run(&output).is_ok());
+            }
+        }
+        // Not even the empty fuzz run fits
+        let mut buffer = input.clone();
+        assert!(mutator.fuzz(&mut buffer, None, 1).unwrap().is_none());
+        // Including the blank fuzz run for inputs that are too short
+        assert!(mutator.fuzz(&mut [], None, 1).unwrap().is_none());
+
+        // Contents of nested pointers are shrunk instead of dropping the call
+        let mut nested_json = json.clone();
+        nested_json["functions"][0]["parameter_types"][0]["type"] =
+            serde_json::json!({ "pointee": { "pointee": "char" } });
+        let mut mutator = seeded_mutator(&nested_json);
+        let inner = vec![serde_json::json!({ "int": 7 }); 4096];
+        let fuzz_run: FuzzRun = serde_json::from_value(serde_json::json!({
+            "calls": [{ "function": "read", "arguments": [{ "fuzz_input": { "pointer": [{ "pointer": inner }] } }] }]
+        }))
+        .unwrap();
+        let input = mutator.encode_fuzz_run(&fuzz_run).unwrap();
+        let mut kept = 0;
+        for _ in 0..20 {
+            let output = mutator
+                .fuzz(&mut input.clone(), None, 1000)
+                .unwrap()
+                .expect("Mutation should be shrunk to fit max_size")
+                .to_vec();
+            assert!(output.len() <= 1000);
+            let calls = mutator.decode_fuzz_run(&output).unwrap().calls;
+            kept += calls.iter().any(|call| call.function == "read") as usize;
+        }
+        // Some mutations remove the call on their own
+        assert!(kept > 10, "{} of 20", kept);
+    }
+
+    #[test]
+    fn auto_driver_mutator_trims_structurally() {
+        let mut mutator = seeded_mutator(&read_pointer_api());
+        let pointer = |first: i128| {
+            let mut elements = vec![1; 300];
+            elements[0] = first;
+            elements
+        };
+        let input = read_pointer_input(&mutator, &[pointer(1), pointer(42), pointer(1)]);
+        // Stands in for the target: the behavior is kept as long as some pointer starts with 42
+        let keeps_behavior = |fuzz_run: &FuzzRun| {
+            fuzz_run.calls.iter().any(|call| {
+                matches!(&call.arguments[0], Argument::FuzzInput(Value::Pointer(elements))
+                    if elements.first() == Some(&Value::Int(42)))
+            })
+        };
+
+        let steps = mutator.init_trim(&input);
+        assert!(steps > 0);
+        let mut step = 0;
+        let mut trimmed = input.clone();
+        while step < steps {
+            let candidate = mutator.trim().to_vec();
+            let success = keeps_behavior(&mutator.decode_fuzz_run(&candidate).unwrap());
+            if success {
+                trimmed = candidate;
+            }
+            step = mutator.post_trim(success);
+        }
+        let fuzz_run = mutator.decode_fuzz_run(&trimmed).unwrap();
+        assert!(keeps_behavior(&fuzz_run));
+        assert_eq!(fuzz_run.calls.len(), 1);
+        assert!(matches!(&fuzz_run.calls[0].arguments[0],
+            Argument::FuzzInput(Value::Pointer(elements)) if elements.len() == 1));
+        assert!(trimmed.len() < input.len() / 100);
+
+        // Inputs that do not fit the function API are left alone
+        assert_eq!(mutator.init_trim(&[1, 0, 0b1000_0000, 5]), 0);
+    }
+
+    #[test]
+    fn auto_driver_mutator_afl_exports() {
+        use std::ffi::{c_char, c_uint, c_void, CStr};
+        use std::{ptr, slice};
+
+        extern "C" {
+            fn afl_custom_init(afl: *mut c_void, seed: c_uint) -> *mut c_void;
+            fn afl_custom_fuzz(
+                data: *mut c_void,
+                buf: *mut u8,
+                buf_size: usize,
+                out_buf: *mut *const u8,
+                add_buf: *const u8,
+                add_buf_size: usize,
+                max_size: usize,
+            ) -> usize;
+            fn afl_custom_describe(data: *mut c_void, max_description_len: usize) -> *const c_char;
+            fn afl_custom_init_trim(data: *mut c_void, buf: *const u8, buf_size: usize) -> i32;
+            fn afl_custom_trim(data: *mut c_void, out_buf: *mut *const u8) -> usize;
+            fn afl_custom_post_trim(data: *mut c_void, success: u8) -> i32;
+            fn afl_custom_deinit(data: *mut c_void);
+        }
+
+        let function_api_location = write_function_api(
+            "auto_driver_mutator_afl.json",
+            &serde_json::to_string(&mock_driver_api()).unwrap(),
+        );
+        env::set_var("AUTO_DRIVER_FUNCTION_API_PATH", &function_api_location);
+        let mutator = AutoDriverMutator::new(&mock_driver_api()).unwrap();
+        let data = unsafe { afl_custom_init(ptr::null_mut(), 0) };
+        assert!(!data.is_null());
+
+        let mut input = vec![0];
+        for _ in 0..200 {
+            let mut out_buf = ptr::null();
+            let size = unsafe {
+                afl_custom_fuzz(
+                    data,
+                    input.as_mut_ptr(),
+                    input.len(),
+                    &mut out_buf,
+                    ptr::null(),
+                    0,
+                    4096,
+                )
+            };
+            assert!(size > 0 && size <= 4096);
+            input = unsafe { slice::from_raw_parts(out_buf, size) }.to_vec();
+            let description = unsafe { CStr::from_ptr(afl_custom_describe(data, 64)) };
+            assert!(!description.to_bytes().is_empty());
+        }
+        assert!(!mutator.decode_fuzz_run(&input).unwrap().calls.is_empty());
+
+        // Trimming runs on the instance AFL++ created
+        let steps = unsafe { afl_custom_init_trim(data, input.as_ptr(), input.len()) };
+        assert!(steps > 0);
+        let mut step = 0;
+        while step < steps {
+            let mut out_buf = ptr::null();
+            let size = unsafe { afl_custom_trim(data, &mut out_buf) };
+            let candidate = unsafe { slice::from_raw_parts(out_buf, size) };
+            assert!(candidate.len() <= input.len());
+            assert!(mutator.decode_fuzz_run(candidate).is_ok());
+            step = unsafe { afl_custom_post_trim(data, 0) };
+        }
+        unsafe { afl_custom_deinit(data) };
+    }
+
+    /// API of a mock fuzz-driver for the LibAFL tests
+    fn mock_driver_api() -> FunctionApi {
+        FunctionApi::from_json(&serde_json::json!({
+            "decision_bits_per_iteration": 5,
+            "minimal_init_chaining_variables_size": 0,
+            "types": [],
+            "functions": [{
+                "name": "open",
+                "return_type": { "type": { "pointee": "char" } },
+                "parameter_types": []
+            }, {
+                "name": "write",
+                "return_type": { "type": "void" },
+                "parameter_types": [
+                    { "type": { "pointee": "char" }, "opaque": true },
+                    { "type": { "pointee": "char" }, "opaque": false }
+                ]
+            }, {
+                "name": "close",
+                "return_type": { "type": "void" },
+                "parameter_types": [{ "type": { "pointee": "char" }, "opaque": true }]
+            }]
+        }))
+        .unwrap()
+    }
+
+    /// Mock fuzz-driver for [`mock_driver_api`], which crashes when a handle is closed
+    /// after "A..." was written to it, and signals how far it got on the way there
+    fn run_mock_driver(fuzz_run: &FuzzRun, signal: impl Fn(usize)) -> libafl::executors::ExitKind {
+        let (mut handles, mut written) = (0, false);
+        for call in fuzz_run.calls.iter() {
+            match (call.function.as_str(), &call.arguments[..]) {
+                ("open", _) if call.chain_return == Some(true) => {
+                    signal(0);
+                    handles += 1;
+                }
+                ("write", [_, Argument::FuzzInput(Value::Pointer(data))]) if handles > 0 => {
+                    signal(1);
+                    if data.first() == Some(&Value::Int(b'A' as i128)) {
+                        signal(2);
+                        written = true;
+                    }
+                }
+                ("close", _) if handles > 0 => {
+                    handles -= 1;
+                    if written {
+                        signal(3);
+                        return libafl::executors::ExitKind::Crash;
+                    }
+                }
+                _ => {}
+            }
+        }
+        libafl::executors::ExitKind::Ok
+    }
+
+    #[test]
+    fn auto_driver_mutator_drives_libafl_fuzzer() {
+        use libafl::corpus::{Corpus, InMemoryCorpus};
+        use libafl::events::SimpleEventManager;
+        use libafl::executors::{ExitKind, InProcessExecutor};
+        use libafl::feedbacks::{CrashFeedback, MaxMapFeedback};
+        use libafl::inputs::{BytesInput, HasBytesVec};
+        use libafl::monitors::SimpleMonitor;
+        use libafl::observers::StdMapObserver;
+        use libafl::schedulers::QueueScheduler;
+        use libafl::stages::StdMutationalStage;
+        use libafl::state::{HasSolutions, StdState};
+        use libafl::{Evaluator, Fuzzer, StdFuzzer};
+        use libafl_bolts::rands::StdRand;
+        use libafl_bolts::tuples::tuple_list;
+
+        let mut mutator = AutoDriverMutator::new(&mock_driver_api()).unwrap();
+        mutator.set_seed(0);
+        let target = AutoDriverMutator::new(&mock_driver_api()).unwrap();
+
+        let mut signals = [0u8; 4];
+        let signals_ptr = signals.as_mut_ptr();
+        let mut harness = |input: &BytesInput| match target.decode_fuzz_run(input.bytes()) {
+            Ok(fuzz_run) => {
+                run_mock_driver(&fuzz_run, |index| unsafe { *signals_ptr.add(index) = 1 })
+            }
+            Err(_) => ExitKind::Ok,
+        };
+
+        let observer =
+            unsafe { StdMapObserver::from_mut_ptr("signals", signals_ptr, signals.len()) };
+        let mut feedback = MaxMapFeedback::new(&observer);
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut mgr = SimpleEventManager::new(SimpleMonitor::new(|_| {}));
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(observer),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+        // Start from a fuzz run without any calls
+        fuzzer
+            .add_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![0, 0]),
+            )
+            .unwrap();
+
+        let mut stages = tuple_list!(StdMutationalStage::new(mutator));
+        for _ in 0..2000 {
+            fuzzer
+                .fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr)
+                .unwrap();
+            if state.solutions().count() > 0 {
+                break;
+            }
+        }
+        assert!(state.solutions().count() > 0);
+    }
+
+    #[test]
+    fn auto_driver_mutator_drives_libafl_fuzzer_on_structured_inputs() {
+        use std::cell::RefCell;
+
+        use libafl::corpus::{Corpus, InMemoryCorpus};
+        use libafl::events::SimpleEventManager;
+        use libafl::executors::InProcessExecutor;
+        use libafl::feedbacks::{CrashFeedback, MaxMapFeedback};
+        use libafl::monitors::SimpleMonitor;
+        use libafl::observers::StdMapObserver;
+        use libafl::schedulers::QueueScheduler;
+        use libafl::stages::StdMutationalStage;
+        use libafl::state::{HasSolutions, StdState};
+        use libafl::{Evaluator, Fuzzer, StdFuzzer};
+        use libafl_bolts::rands::StdRand;
+        use libafl_bolts::tuples::tuple_list;
+
+        use auto_driver_mutator::libafl_input::AutoDriverInput;
+
+        let mut mutator = AutoDriverMutator::new(&mock_driver_api()).unwrap();
+        mutator.set_seed(0);
+        let target = AutoDriverMutator::new(&mock_driver_api()).unwrap();
+
+        let mut signals = [0u8; 4];
+        let signals_ptr = signals.as_mut_ptr();
+        let crashing_input = RefCell::new(None);
+        // Inputs are only encoded for execution, the mock fuzz-driver then reads the bytes as usual
+        let mut harness = |input: &AutoDriverInput| {
+            let buffer = input.to_bytes(&target).unwrap();
+            let fuzz_run = target.decode_fuzz_run(&buffer).unwrap();
+            assert_eq!(fuzz_run, input.fuzz_run());
+            let exit_kind =
+                run_mock_driver(&fuzz_run, |index| unsafe { *signals_ptr.add(index) = 1 });
+            if exit_kind == libafl::executors::ExitKind::Crash {
+                *crashing_input.borrow_mut() = Some(input.clone());
+            }
+            exit_kind
+        };
+
+        let observer =
+            unsafe { StdMapObserver::from_mut_ptr("signals", signals_ptr, signals.len()) };
+        let mut feedback = MaxMapFeedback::new(&observer);
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut mgr = SimpleEventManager::new(SimpleMonitor::new(|_| {}));
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(observer),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+        fuzzer
+            .add_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                AutoDriverInput::new(FuzzRun { calls: Vec::new() }),
+            )
+            .unwrap();
+
+        let mut stages = tuple_list!(StdMutationalStage::new(mutator));
+        for _ in 0..2000 {
+            fuzzer
+                .fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr)
+                .unwrap();
+            if state.solutions().count() > 0 {
+                break;
+            }
+        }
+        assert!(state.solutions().count() > 0);
+
+        // Inputs survive the serialization LibAFL uses for storing and sending them
+        let crashing_input = crashing_input.into_inner().unwrap();
+        let json = serde_json::to_string(&crashing_input).unwrap();
+        assert_eq!(
+            serde_json::from_str::<AutoDriverInput>(&json).unwrap(),
+            crashing_input
+        );
+    }
+
+    #[cfg(feature = "libfuzzer")]
+    #[test]
+    fn auto_driver_mutator_libfuzzer_exports() {
+        extern "C" {
+            fn LLVMFuzzerCustomMutator(
+                data: *mut u8,
+                size: usize,
+                max_size: usize,
+                seed: u32,
+            ) -> usize;
+            fn LLVMFuzzerCustomCrossOver(
+                data1: *const u8,
+                size1: usize,
+                data2: *const u8,
+                size2: usize,
+                out: *mut u8,
+                max_out_size: usize,
+                seed: u32,
+            ) -> usize;
+        }
+
+        let function_api_location = env::temp_dir().join("auto_driver_mutator_libfuzzer.json");
+        let api = mock_driver_api();
+        fs::write(&function_api_location, serde_json::to_string(&api).unwrap()).unwrap();
+        env::set_var("AUTO_DRIVER_FUNCTION_API_PATH", &function_api_location);
+        let mutator = AutoDriverMutator::new(&api).unwrap();
+
+        let mutate = |input: &[u8], max_size: usize, seed: u32| {
+            let mut data = input.to_vec();
+            data.resize(max_size.max(input.len()), 0);
+            let size =
+                unsafe { LLVMFuzzerCustomMutator(data.as_mut_ptr(), input.len(), max_size, seed) };
+            data.truncate(size);
+            data
+        };
+        let mut inputs = vec![mutate(&[], 4096, 0)];
+        for seed in 0..200 {
+            let mutated = mutate(inputs.last().unwrap(), 4096, seed);
+            assert!(mutated.len() <= 4096);
+            assert!(mutator.decode_fuzz_run(&mutated).is_ok());
+            inputs.push(mutated);
+        }
+        // The same seed gives the same mutation
+        assert_eq!(mutate(&inputs[100], 4096, 7), mutate(&inputs[100], 4096, 7));
+        // Mutations that do not fit leave the input unchanged
+        assert_eq!(mutate(&[], 1, 0), Vec::<u8>::new());
+        assert_eq!(mutate(&inputs[100], 1, 0), inputs[100]);
+
+        for seed in 0..200 {
+            let (data1, data2) = (&inputs[seed as usize], &inputs[199 - seed as usize]);
+            let mut out = vec![0; 4096];
+            let size = unsafe {
+                LLVMFuzzerCustomCrossOver(
+                    data1.as_ptr(),
+                    data1.len(),
+                    data2.as_ptr(),
+                    data2.len(),
+                    out.as_mut_ptr(),
+                    out.len(),
+                    seed,
+                )
+            };
+            assert!(size > 0);
+            assert!(mutator.decode_fuzz_run(&out[..size]).is_ok());
+        }
+    }
+
+    #[test]
+    fn auto_driver_mutator_describes_mutations() {
+        let mut mutator = AutoDriverMutator::new(&mock_driver_api()).unwrap();
+        mutator.set_seed(0);
+        let mut buffer = vec![0];
+        let mut descriptions = std::collections::BTreeSet::new();
+        for i in 0..2000 {
+            let add_buff = (i % 2 == 0).then(|| buffer.clone());
+            buffer = mutator
+                .fuzz(&mut buffer.clone(), add_buff.as_deref(), 4096)
+                .unwrap()
+                .unwrap()
+                .to_vec();
+            let description = mutator.describe(usize::MAX).unwrap().unwrap().to_string();
+            let truncated = mutator.describe(8).unwrap().unwrap();
+            assert!(description.starts_with(truncated) && truncated.len() <= 8);
+            descriptions.insert(description);
+        }
+        for expected in [
+            "init",
+            "add_call:open",
+            "add_call:write",
+            "remove_call:close",
+            "move_call:write",
+            "duplicate_call:open",
+            "mutate_call:open",
+            "mutate_arg:1",
+            "chain_arg:1",
+            "splice:crossover",
+        ] {
+            assert!(
+                descriptions.contains(expected),
+                "{} not in {:?}",
+                expected,
+                descriptions
+            );
+        }
+
+        // Mostly chained arguments, which can be turned back into fuzz input
+        let mut calls = vec![
+            serde_json::json!({
+                "function": "write",
+                "arguments": ["permanently_chained", "chained"]
+            });
+            16
+        ];
+        calls.insert(
+            0,
+            serde_json::json!({ "function": "open", "chain_return": true, "arguments": [] }),
+        );
+        let fuzz_run: FuzzRun =
+            serde_json::from_value(serde_json::json!({ "calls": calls })).unwrap();
+        let input = mutator.encode_fuzz_run(&fuzz_run).unwrap();
+        assert!((0..500).any(|_| {
+            mutator.fuzz(&mut input.clone(), None, usize::MAX).unwrap();
+            mutator.describe(usize::MAX).unwrap() == Some("unchain_arg:1")
+        }));
+    }
+
+    #[test]
+    fn auto_driver_mutator_learns_from_queue_entries() {
+        let mut mutator = AutoDriverMutator::new(&mock_driver_api()).unwrap();
+        mutator.set_seed(0);
+        let data = serde_json::json!({ "pointer": [{ "int": 65 }, { "int": 66 }] });
+        let fuzz_run: FuzzRun = serde_json::from_value(serde_json::json!({
+            "calls": [
+                { "function": "open", "chain_return": true, "arguments": [] },
+                { "function": "write", "arguments": ["permanently_chained", { "fuzz_input": data }] }
+            ]
+        }))
+        .unwrap();
+        let entry = mutator.encode_fuzz_run(&fuzz_run).unwrap();
+        assert!(!mutator.learn_from_queue_entry(&entry[..entry.len() - 1]));
+
+        // Through the AFL++ hook
+        let queue_entry = env::temp_dir().join("auto_driver_mutator_queue_entry");
+        fs::write(&queue_entry, &entry).unwrap();
+        assert!(!mutator
+            .queue_new_entry(queue_entry.as_os_str(), None)
+            .unwrap());
+        for _ in 0..19 {
+            assert!(mutator.learn_from_queue_entry(&entry));
+        }
+
+        let mut added = std::collections::HashMap::new();
+        let (mut chained_returns, mut returns) = (0, 0);
+        let mut pooled_arguments = 0;
+        for _ in 0..3000 {
+            let output = mutator
+                .fuzz(&mut entry.clone(), None, usize::MAX)
+                .unwrap()
+                .unwrap()
+                .to_vec();
+            let description = mutator.describe(usize::MAX).unwrap().unwrap().to_string();
+            let calls = mutator.decode_fuzz_run(&output).unwrap().calls;
+            if let Some(function) = description.strip_prefix("add_call:") {
+                *added.entry(function.to_string()).or_insert(0) += 1;
+            } else if description == "mutate_call:open" {
+                returns += 1;
+                chained_returns += (calls[0].chain_return == Some(true)) as usize;
+            } else if description == "pool_arg:1" {
+                pooled_arguments += 1;
+                let pooled =
+                    Argument::FuzzInput(Value::Pointer(vec![Value::Int(65), Value::Int(66)]));
+                assert!(calls
+                    .iter()
+                    .any(|call| call.function == "write" && call.arguments[1] == pooled));
+            }
+        }
+        // Functions of new queue entries are preferred for new calls
+        assert!(added["open"] > 2 * added["close"]);
+        assert!(added["write"] > 2 * added["close"]);
+        // Return values are chained as often as in the queue entries
+        assert!(chained_returns * 10 > returns * 8);
+        assert!(pooled_arguments > 0);
+    }
 }